@@ -0,0 +1,270 @@
+use actix_web::HttpResponse;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::future::Future;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::auth::Caller;
+use crate::entities::{AppState, RegistryCredentials};
+use crate::manifest_builder::COMPONENT_TYPE_ANNOTATION;
+use crate::services::{calculate_sha256, fetch_manifest_raw};
+use crate::webhooks::{WebhookEvent, WebhookPayload};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Push,
+    Update,
+    Patch,
+    Rollback,
+    Restore,
+    Trash,
+    Delete,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub timestamp: DateTime<Utc>,
+    pub caller: String,
+    pub action: AuditAction,
+    pub repository: String,
+    // Absente pour les opérations portant sur tout le dépôt
+    pub reference: Option<String>,
+    pub old_digest: Option<String>,
+    pub new_digest: Option<String>,
+    pub outcome: AuditOutcome,
+    // Statut HTTP renvoyé à l'appelant ; absent pour les opérations du service
+    pub status: Option<u16>,
+}
+
+// Journal en lignes JSON, ouvert en ajout seul ; désactivé si aucun fichier n'est fourni
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    file: Mutex<Option<File>>,
+}
+
+impl AuditLog {
+    pub fn open(path: Option<PathBuf>) -> std::io::Result<Self> {
+        let file = match &path {
+            Some(path) => Some(OpenOptions::new().create(true).append(true).open(path)?),
+            None => None,
+        };
+        Ok(AuditLog {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn record(&self, event: &AuditEvent) {
+        let mut file = self.file.lock().unwrap();
+        let Some(file) = file.as_mut() else {
+            return;
+        };
+        let result = serde_json::to_string(event)
+            .map_err(|e| e.to_string())
+            .and_then(|line| writeln!(file, "{}", line).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            tracing::error!(error = %e, "Erreur écriture journal d'audit");
+        }
+    }
+
+    pub fn query(
+        &self,
+        repository: Option<&str>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<AuditEvent>, String> {
+        let Some(path) = &self.path else {
+            return Err("Journal d'audit désactivé".to_string());
+        };
+        let file = File::open(path).map_err(|e| format!("Erreur lecture journal: {}", e))?;
+
+        let mut events = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line.map_err(|e| format!("Erreur lecture journal: {}", e))?;
+            // Une ligne tronquée (arrêt brutal pendant l'écriture) est ignorée
+            let Ok(event) = serde_json::from_str::<AuditEvent>(&line) else {
+                continue;
+            };
+            if repository.is_some_and(|repository| event.repository != repository)
+                || from.is_some_and(|from| event.timestamp < from)
+                || to.is_some_and(|to| event.timestamp > to)
+            {
+                continue;
+            }
+            events.push(event);
+        }
+        Ok(events)
+    }
+}
+
+struct TaggedManifest {
+    digest: String,
+    component_type: Option<String>,
+}
+
+// Manifest actuellement pointé par la référence ; None s'il n'existe pas ou est illisible
+async fn tagged_manifest(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    reference: Option<&str>,
+) -> Option<TaggedManifest> {
+    let client = state.registry_client.lock().unwrap().clone();
+    let raw = fetch_manifest_raw(
+        &client,
+        &state.zot_config.url,
+        repository,
+        reference?,
+        credentials,
+    )
+    .await
+    .ok()
+    .flatten()?;
+
+    let component_type = serde_json::from_slice::<serde_json::Value>(&raw)
+        .ok()
+        .and_then(|manifest| {
+            manifest
+                .get("annotations")?
+                .get(COMPONENT_TYPE_ANNOTATION)?
+                .as_str()
+                .map(|value| value.to_string())
+        });
+    Some(TaggedManifest {
+        digest: calculate_sha256(&raw),
+        component_type,
+    })
+}
+
+// Exécute une opération de modification et consigne son résultat, avec le digest pointé
+// par la référence avant et après l'opération ; en cas de succès, l'événement est diffusé
+// aux webhooks et aux flux SSE
+pub async fn audited(
+    state: &AppState,
+    caller: &Caller,
+    action: AuditAction,
+    repository: &str,
+    reference: Option<&str>,
+    operation: impl Future<Output = HttpResponse>,
+) -> HttpResponse {
+    let old = tagged_manifest(state, &caller.credentials, repository, reference).await;
+    let response = operation.await;
+    let new = tagged_manifest(state, &caller.credentials, repository, reference).await;
+
+    let status = response.status();
+    let outcome = if status.is_success() {
+        AuditOutcome::Success
+    } else {
+        AuditOutcome::Failure
+    };
+    let record = Record {
+        actor: &caller.name,
+        action,
+        repository,
+        reference,
+        outcome,
+        status: Some(status.as_u16()),
+    };
+    record_and_notify(state, record, old, new);
+    response
+}
+
+// Même traitement pour une opération déclenchée par le service lui-même (rétention, purge
+// de la corbeille), au nom d'un acteur système. `target` désigne le manifest concerné avant
+// l'opération : la référence, ou le digest d'une version déjà en corbeille
+#[allow(clippy::too_many_arguments)]
+pub async fn audited_task<T, E>(
+    state: &AppState,
+    actor: &str,
+    credentials: &RegistryCredentials,
+    action: AuditAction,
+    repository: &str,
+    reference: &str,
+    target: &str,
+    operation: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    let old = tagged_manifest(state, credentials, repository, Some(target)).await;
+    let result = operation.await;
+    let new = tagged_manifest(state, credentials, repository, Some(reference)).await;
+
+    let record = Record {
+        actor,
+        action,
+        repository,
+        reference: Some(reference),
+        outcome: if result.is_ok() {
+            AuditOutcome::Success
+        } else {
+            AuditOutcome::Failure
+        },
+        status: None,
+    };
+    record_and_notify(state, record, old, new);
+    result
+}
+
+struct Record<'a> {
+    actor: &'a str,
+    action: AuditAction,
+    repository: &'a str,
+    reference: Option<&'a str>,
+    outcome: AuditOutcome,
+    status: Option<u16>,
+}
+
+fn record_and_notify(
+    state: &AppState,
+    record: Record,
+    old: Option<TaggedManifest>,
+    new: Option<TaggedManifest>,
+) {
+    let event = AuditEvent {
+        timestamp: Utc::now(),
+        caller: record.actor.to_string(),
+        action: record.action,
+        repository: record.repository.to_string(),
+        reference: record.reference.map(|reference| reference.to_string()),
+        old_digest: old.as_ref().map(|old| old.digest.clone()),
+        new_digest: new.as_ref().map(|new| new.digest.clone()),
+        outcome: record.outcome,
+        status: record.status,
+    };
+    state.audit_log.record(&event);
+
+    let webhook_event = match record.action {
+        AuditAction::Push => Some(WebhookEvent::Published),
+        AuditAction::Update | AuditAction::Patch => Some(WebhookEvent::Updated),
+        AuditAction::Trash | AuditAction::Delete => Some(WebhookEvent::Deleted),
+        AuditAction::Rollback | AuditAction::Restore => None,
+    };
+    if let (Some(webhook_event), AuditOutcome::Success) = (webhook_event, event.outcome) {
+        // Après une suppression, seul le manifest retiré décrit encore la version
+        let manifest = if webhook_event == WebhookEvent::Deleted {
+            old
+        } else {
+            new
+        };
+        let payload = WebhookPayload {
+            event: webhook_event,
+            timestamp: event.timestamp,
+            repository: event.repository,
+            version: event.reference,
+            digest: manifest.as_ref().map(|manifest| manifest.digest.clone()),
+            component_type: manifest.and_then(|manifest| manifest.component_type),
+            actor: event.caller,
+        };
+        state.event_bus.publish(payload.clone());
+        state.webhook_queue.enqueue(&state.webhook_config, payload);
+    }
+}
@@ -0,0 +1,191 @@
+use actix_web::dev::Payload;
+use actix_web::error::ErrorUnauthorized;
+use actix_web::{web, FromRequest, HttpRequest, HttpResponse};
+use base64::{engine::general_purpose, Engine as _};
+use futures::future::LocalBoxFuture;
+use serde::Deserialize;
+
+use crate::entities::{AppState, RegistryCredentials};
+use crate::jwt::authenticate_bearer;
+use crate::services::{calculate_sha256, glob_match};
+
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+// Chaque rôle inclut les droits des rôles précédents
+#[derive(Deserialize, Clone, Copy, PartialEq, PartialOrd, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Reader,
+    Publisher,
+    Admin,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct Grant {
+    pub role: Role,
+    // Motif glob sur le nom du dépôt
+    #[serde(default = "match_all")]
+    pub repositories: String,
+}
+
+fn match_all() -> String {
+    "*".to_string()
+}
+
+#[derive(Deserialize, Clone)]
+pub struct ApiKey {
+    pub name: String,
+    // SHA-256 de la clé, en hexadécimal (préfixe `sha256:` facultatif)
+    pub key_sha256: String,
+    pub grants: Vec<Grant>,
+}
+
+// Identité de l'appelant, résolue à partir de la clé d'API ou du JWT de la requête
+#[derive(Clone)]
+pub struct Caller {
+    pub name: String,
+    pub grants: Vec<Grant>,
+    // Identifiants à présenter à Zot pour le compte de l'appelant
+    pub credentials: RegistryCredentials,
+}
+
+impl Caller {
+    // Authentification désactivée : accès complet
+    fn anonymous(credentials: RegistryCredentials) -> Self {
+        Caller {
+            name: "anonymous".to_string(),
+            grants: vec![Grant {
+                role: Role::Admin,
+                repositories: match_all(),
+            }],
+            credentials,
+        }
+    }
+
+    pub fn can(&self, role: Role, repository: &str) -> bool {
+        self.grants
+            .iter()
+            .any(|grant| grant.role >= role && glob_match(&grant.repositories, repository))
+    }
+
+    pub fn require(&self, role: Role, repository: &str) -> Result<(), HttpResponse> {
+        if self.can(role, repository) {
+            Ok(())
+        } else {
+            Err(HttpResponse::Forbidden()
+                .body(format!("Droits {:?} requis sur {}", role, repository)))
+        }
+    }
+
+    // Au moins un dépôt avec ce rôle : refus anticipé quand le dépôt visé n'est pas encore connu
+    pub fn require_any(&self, role: Role) -> Result<(), HttpResponse> {
+        if self.grants.iter().any(|grant| grant.role >= role) {
+            Ok(())
+        } else {
+            Err(HttpResponse::Forbidden().body(format!("Droits {:?} requis", role)))
+        }
+    }
+
+    // Opérations d'administration portant sur tous les dépôts
+    pub fn require_global(&self, role: Role) -> Result<(), HttpResponse> {
+        if self
+            .grants
+            .iter()
+            .any(|grant| grant.role >= role && grant.repositories == "*")
+        {
+            Ok(())
+        } else {
+            Err(HttpResponse::Forbidden().body(format!("Droits {:?} globaux requis", role)))
+        }
+    }
+}
+
+// En mode passthrough, les identifiants Basic ou Bearer de la requête sont transmis tels quels
+fn registry_credentials(
+    state: &AppState,
+    authorization: Option<&str>,
+) -> Result<RegistryCredentials, actix_web::Error> {
+    if !state.zot_config.passthrough {
+        return Ok(state.zot_config.service_credentials());
+    }
+
+    let authorization =
+        authorization.ok_or_else(|| ErrorUnauthorized("Identifiants du registre manquants"))?;
+    if let Some(token) = authorization.strip_prefix("Bearer ") {
+        return Ok(RegistryCredentials::Bearer(token.trim().to_string()));
+    }
+    let encoded = authorization
+        .strip_prefix("Basic ")
+        .ok_or_else(|| ErrorUnauthorized("Schéma d'authentification non supporté"))?;
+    let decoded = general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+        .ok_or_else(|| ErrorUnauthorized("Identifiants Basic invalides"))?;
+    let (username, password) = decoded
+        .split_once(':')
+        .ok_or_else(|| ErrorUnauthorized("Identifiants Basic invalides"))?;
+    Ok(RegistryCredentials::Basic {
+        username: username.to_string(),
+        password: password.to_string(),
+    })
+}
+
+async fn authenticate(
+    state: web::Data<AppState>,
+    api_key: Option<String>,
+    authorization: Option<String>,
+) -> Result<Caller, actix_web::Error> {
+    let credentials = registry_credentials(&state, authorization.as_deref())?;
+    let auth = &state.auth_config;
+    if !auth.enabled {
+        return Ok(Caller::anonymous(credentials));
+    }
+
+    if let Some(token) = authorization
+        .as_deref()
+        .and_then(|value| value.strip_prefix("Bearer "))
+    {
+        return authenticate_bearer(&state, token.trim(), credentials)
+            .await
+            .map_err(ErrorUnauthorized);
+    }
+
+    let key = api_key.ok_or_else(|| ErrorUnauthorized("Clé d'API ou jeton manquant"))?;
+    let hash = calculate_sha256(key.as_bytes());
+
+    auth.api_keys
+        .iter()
+        .find(|api_key| {
+            let expected = api_key.key_sha256.to_lowercase();
+            hash.strip_prefix("sha256:") == Some(expected.trim_start_matches("sha256:"))
+        })
+        .map(|api_key| Caller {
+            name: api_key.name.clone(),
+            grants: api_key.grants.clone(),
+            credentials,
+        })
+        .ok_or_else(|| ErrorUnauthorized("Clé d'API invalide"))
+}
+
+impl FromRequest for Caller {
+    type Error = actix_web::Error;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let state = req
+            .app_data::<web::Data<AppState>>()
+            .expect("AppState est toujours enregistré")
+            .clone();
+        let header = |name: &str| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string())
+        };
+        let api_key = header(API_KEY_HEADER);
+        let authorization = header("Authorization");
+
+        Box::pin(authenticate(state, api_key, authorization))
+    }
+}
@@ -0,0 +1,490 @@
+use jsonwebtoken::Algorithm;
+use serde::Deserialize;
+use std::fs;
+use std::io::ErrorKind;
+
+use crate::auth::{ApiKey, Role};
+use crate::entities::ZotConfig;
+use crate::webhooks::WebhookEvent;
+
+// Configuration chargée depuis le fichier JSON désigné par POC_CONFIG (config.json par défaut) ;
+// chaque section absente garde ses valeurs par défaut
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub zot: ZotConfig,
+    // Répertoire des données persistées par le service (registre du GC...)
+    pub data_dir: String,
+    pub trash: TrashConfig,
+    pub gc: GcConfig,
+    pub history: HistoryConfig,
+    pub retention: RetentionConfig,
+    pub auth: AuthConfig,
+    pub audit: AuditConfig,
+    pub webhooks: WebhookConfig,
+    pub events: EventsConfig,
+    pub telemetry: TelemetryConfig,
+    pub health: HealthConfig,
+    pub upstream: UpstreamConfig,
+    pub server: ServerConfig,
+    pub uploads: UploadConfig,
+    pub idempotency: IdempotencyConfig,
+    pub jobs: JobsConfig,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            zot: ZotConfig::default(),
+            data_dir: "data".to_string(),
+            trash: TrashConfig::default(),
+            gc: GcConfig::default(),
+            history: HistoryConfig::default(),
+            retention: RetentionConfig::default(),
+            auth: AuthConfig::default(),
+            audit: AuditConfig::default(),
+            webhooks: WebhookConfig::default(),
+            events: EventsConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            health: HealthConfig::default(),
+            upstream: UpstreamConfig::default(),
+            server: ServerConfig::default(),
+            uploads: UploadConfig::default(),
+            idempotency: IdempotencyConfig::default(),
+            jobs: JobsConfig::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct AuditConfig {
+    pub enabled: bool,
+    // Fichier de lignes JSON ; `{data_dir}/audit.jsonl` par défaut
+    pub path: Option<String>,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        AuditConfig {
+            enabled: true,
+            path: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct TrashConfig {
+    pub retention_hours: i64,
+    pub purge_interval_minutes: u64,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        TrashConfig {
+            retention_hours: 7 * 24,
+            purge_interval_minutes: 60,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct GcConfig {
+    pub enabled: bool,
+    pub interval_minutes: u64,
+    pub grace_period_minutes: i64,
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        GcConfig {
+            enabled: true,
+            interval_minutes: 6 * 60,
+            grace_period_minutes: 24 * 60,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct HistoryConfig {
+    // Versions conservées par tag pour le rollback, version courante comprise. Chaque entrée
+    // garde ses blobs référencés : la config et les couches remplacées par une mise à jour ne
+    // sont récupérées par le GC qu'une fois sorties de l'historique. 1 : aucun rollback possible,
+    // les blobs remplacés sont récupérés dès le délai de grâce écoulé
+    pub max_entries: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig { max_entries: 50 }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct RetentionConfig {
+    pub enabled: bool,
+    pub interval_minutes: u64,
+    // La première politique dont le motif correspond au dépôt s'applique
+    pub policies: Vec<RetentionPolicy>,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        RetentionConfig {
+            enabled: true,
+            interval_minutes: 12 * 60,
+            policies: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct RetentionPolicy {
+    // Motif glob sur le nom du dépôt
+    #[serde(default = "match_all")]
+    pub repositories: String,
+    pub keep_last_releases: Option<usize>,
+    pub prerelease_max_age_days: Option<i64>,
+    // Motifs glob : tout manifest pointé par un de ces tags est conservé
+    #[serde(default)]
+    pub protected_tags: Vec<String>,
+}
+
+fn match_all() -> String {
+    "*".to_string()
+}
+
+#[derive(Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub enabled: bool,
+    // Sans authentification, l'API est ouverte à tous : le service refuse de démarrer tant que
+    // ce choix n'est pas confirmé ici
+    pub allow_anonymous: bool,
+    pub api_keys: Vec<ApiKey>,
+    // Jetons `Authorization: Bearer` émis par le fournisseur d'identité
+    pub jwt: Option<JwtConfig>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct JwtConfig {
+    pub issuer: String,
+    pub audience: Option<String>,
+    // Source du JWKS : fichier local ou URL (prioritairement le fichier)
+    pub jwks_file: Option<String>,
+    pub jwks_url: Option<String>,
+    #[serde(default = "default_jwks_refresh")]
+    pub refresh_minutes: u64,
+    #[serde(default = "default_groups_claim")]
+    pub groups_claim: String,
+    // Claim portant le nom de l'appelant, `sub` à défaut
+    #[serde(default = "default_name_claim")]
+    pub name_claim: String,
+    // Algorithmes de signature acceptés ; l'`alg` d'une clé du JWKS restreint encore ce choix
+    #[serde(default = "default_jwt_algorithms")]
+    pub algorithms: Vec<Algorithm>,
+    #[serde(default)]
+    pub role_mappings: Vec<JwtRoleMapping>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct JwtRoleMapping {
+    pub group: String,
+    pub role: Role,
+    #[serde(default = "match_all")]
+    pub repositories: String,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct WebhookConfig {
+    pub endpoints: Vec<WebhookEndpoint>,
+    pub max_attempts: u32,
+    // Délai avant la 2e tentative, doublé ensuite à chaque échec
+    pub initial_backoff_seconds: u64,
+    pub max_backoff_seconds: u64,
+    pub poll_interval_seconds: u64,
+    pub timeout_seconds: u64,
+    // Livraisons terminées conservées dans le journal
+    pub keep_deliveries: usize,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        WebhookConfig {
+            endpoints: Vec::new(),
+            max_attempts: 8,
+            initial_backoff_seconds: 10,
+            max_backoff_seconds: 60 * 60,
+            poll_interval_seconds: 5,
+            timeout_seconds: 10,
+            keep_deliveries: 1000,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct WebhookEndpoint {
+    pub name: String,
+    pub url: String,
+    // Clé HMAC-SHA256 ; sans clé, les requêtes ne sont pas signées
+    pub secret: Option<String>,
+    // Tous les événements si la liste est vide
+    #[serde(default)]
+    pub events: Vec<WebhookEvent>,
+    #[serde(default = "match_all")]
+    pub repositories: String,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct EventsConfig {
+    // Événements conservés pour la reprise d'un flux SSE (Last-Event-ID)
+    pub buffer_size: usize,
+    pub keep_alive_seconds: u64,
+}
+
+impl Default for EventsConfig {
+    fn default() -> Self {
+        EventsConfig {
+            buffer_size: 1000,
+            keep_alive_seconds: 15,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct HealthConfig {
+    // Délai de réponse de Zot au-delà duquel /readyz échoue
+    pub timeout_seconds: u64,
+    // Durée de réutilisation du dernier résultat, pour ne pas solliciter Zot à chaque sonde
+    pub cache_seconds: u64,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        HealthConfig {
+            timeout_seconds: 2,
+            cache_seconds: 5,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct IdempotencyConfig {
+    // Durée pendant laquelle une Idempotency-Key rejoue la réponse d'origine
+    pub window_hours: u64,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        IdempotencyConfig { window_hours: 24 }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct JobsConfig {
+    // Publications asynchrones exécutées simultanément
+    pub max_concurrent: usize,
+    // Publications en attente d'exécution ; au-delà, la requête est refusée (503). Chacune
+    // garde son fichier temporaire et ses pièces jointes jusqu'à son exécution
+    pub max_queued: usize,
+    // Durée pendant laquelle une tâche terminée reste consultable
+    pub retention_hours: u64,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        JobsConfig {
+            max_concurrent: 2,
+            max_queued: 16,
+            retention_hours: 24,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct UploadConfig {
+    // Limites appliquées pendant la lecture du formulaire ; au-delà, réponse 413
+    pub max_part_bytes: u64,
+    pub max_total_bytes: u64,
+    // Répertoire des fichiers temporaires ; celui du système par défaut
+    pub temp_dir: Option<String>,
+    // Préfixes d'URL (schéma, hôte, port et chemin) depuis lesquels un binaire ou un CRD peut
+    // être téléchargé : l'URL, une fois normalisée, doit désigner ce chemin ou un chemin en
+    // dessous ; vide : téléchargement refusé
+    pub remote_url_prefixes: Vec<String>,
+    // Blobs d'un même composant envoyés simultanément à Zot
+    pub max_parallel_blobs: usize,
+}
+
+impl Default for UploadConfig {
+    fn default() -> Self {
+        UploadConfig {
+            max_part_bytes: 50 * 1024 * 1024,
+            max_total_bytes: 60 * 1024 * 1024,
+            temp_dir: None,
+            remote_url_prefixes: Vec::new(),
+            max_parallel_blobs: 4,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    // HTTPS si présent, HTTP sinon
+    pub tls: Option<TlsConfig>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            tls: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TlsConfig {
+    // Chaîne de certificats PEM, certificat du serveur en premier
+    pub cert_path: String,
+    pub key_path: String,
+    // Fréquence de vérification des fichiers ; un changement est pris en compte sans redémarrage
+    #[serde(default = "default_reload_interval")]
+    pub reload_interval_seconds: u64,
+}
+
+fn default_reload_interval() -> u64 {
+    30
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct UpstreamConfig {
+    pub connect_timeout_seconds: u64,
+    // Durée maximale d'un appel à Zot, transfert du corps compris
+    pub timeout_seconds: u64,
+    // Débit minimal d'un envoi de blob (Kio/s) : son délai est `timeout_seconds` plus la durée
+    // de transfert de sa taille à ce débit
+    pub min_blob_rate_kib: u64,
+    // Reprises des opérations idempotentes sur erreur réseau, 429, 502, 503 ou 504
+    pub max_retries: u32,
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    // Échecs consécutifs qui ouvrent le disjoncteur
+    pub failure_threshold: u32,
+    // Durée pendant laquelle les appels échouent immédiatement avant un appel d'essai
+    pub open_seconds: u64,
+    // Bundle PEM d'autorités ajoutées aux racines du système, pour un Zot signé en interne
+    pub ca_bundle: Option<String>,
+    // Certificat et clé PKCS#8 (PEM) présentés à Zot en mTLS
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    // Désactive la vérification du certificat de Zot ; réservé au développement
+    pub insecure_skip_verify: bool,
+}
+
+impl Default for UpstreamConfig {
+    fn default() -> Self {
+        UpstreamConfig {
+            connect_timeout_seconds: 5,
+            timeout_seconds: 60,
+            min_blob_rate_kib: 256,
+            max_retries: 3,
+            initial_backoff_ms: 200,
+            max_backoff_ms: 5000,
+            failure_threshold: 5,
+            open_seconds: 30,
+            ca_bundle: None,
+            client_cert: None,
+            client_key: None,
+            insecure_skip_verify: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Json,
+    Pretty,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    pub format: LogFormat,
+    // Directive de filtre tracing (`info`, `poc=debug`...) ; RUST_LOG prime si défini
+    pub level: String,
+    // Collecteur OTLP/HTTP, pris en compte avec la feature `otlp`
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig {
+            format: LogFormat::Pretty,
+            level: "info".to_string(),
+            otlp_endpoint: None,
+            service_name: "poc".to_string(),
+        }
+    }
+}
+
+fn default_jwks_refresh() -> u64 {
+    60
+}
+
+fn default_groups_claim() -> String {
+    "groups".to_string()
+}
+
+fn default_name_claim() -> String {
+    "preferred_username".to_string()
+}
+
+fn default_jwt_algorithms() -> Vec<Algorithm> {
+    vec![Algorithm::RS256, Algorithm::PS256, Algorithm::ES256]
+}
+
+// Fichier désigné par POC_CONFIG, `config.json` à défaut
+pub fn config_path() -> String {
+    std::env::var("POC_CONFIG").unwrap_or_else(|_| "config.json".to_string())
+}
+
+// Sans POC_CONFIG, un `config.json` absent donne la configuration par défaut (signalée au
+// démarrage) ; un fichier désigné explicitement doit exister
+pub fn load_config() -> Result<AppConfig, String> {
+    let path = config_path();
+    let config: AppConfig = match fs::read(&path) {
+        Ok(content) => serde_json::from_slice(&content)
+            .map_err(|e| format!("Configuration invalide {}: {}", path, e))?,
+        Err(e) if e.kind() == ErrorKind::NotFound && std::env::var("POC_CONFIG").is_err() => {
+            AppConfig::default()
+        }
+        Err(e) => return Err(format!("Erreur lecture configuration {}: {}", path, e)),
+    };
+
+    if !config.auth.enabled && !config.auth.allow_anonymous {
+        return Err(
+            "Authentification désactivée: activer auth.enabled ou confirmer l'accès anonyme \
+             avec auth.allow_anonymous"
+                .to_string(),
+        );
+    }
+    Ok(config)
+}
@@ -0,0 +1,73 @@
+use actix_web::{delete, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+use crate::audit::{audited, AuditAction};
+use crate::auth::{Caller, Role};
+use crate::deletion::{delete_all_versions, DeleteError};
+use crate::entities::AppState;
+use crate::resilience::error_response;
+use crate::trash::{trash_all_versions, TrashError};
+
+#[derive(Deserialize)]
+pub struct DeleteAllParams {
+    #[serde(default)]
+    pub permanent: bool,
+}
+
+#[delete("/api/v1/{repository}/components")]
+pub async fn delete_all_components(
+    path: web::Path<String>,
+    params: web::Query<DeleteAllParams>,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    let repository = path.into_inner();
+    if let Err(response) = caller.require(Role::Admin, &repository) {
+        return response;
+    }
+
+    let action = if params.permanent {
+        AuditAction::Delete
+    } else {
+        AuditAction::Trash
+    };
+    audited(
+        &state,
+        &caller,
+        action,
+        &repository,
+        None,
+        delete_all(&state, &caller, &repository, &params),
+    )
+    .await
+}
+
+async fn delete_all(
+    state: &AppState,
+    caller: &Caller,
+    repository: &str,
+    params: &DeleteAllParams,
+) -> HttpResponse {
+    if !params.permanent {
+        return match trash_all_versions(state, &caller.credentials, repository).await {
+            Ok(entries) => HttpResponse::Ok().json(entries),
+            Err(TrashError::NotFound) => HttpResponse::NotFound().body("Composant non trouvé"),
+            Err(TrashError::AlreadyTrashed) => HttpResponse::Conflict()
+                .body("Une autre version de même référence est déjà en corbeille"),
+            Err(TrashError::Registry(e)) => error_response(e.context("Erreur")),
+            Err(TrashError::Conflict | TrashError::Expired) => {
+                HttpResponse::InternalServerError().body("Erreur: état de corbeille inattendu")
+            }
+        };
+    }
+
+    match delete_all_versions(state, &caller.credentials, repository).await {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(DeleteError::NotFound) => HttpResponse::NotFound().body("Composant non trouvé"),
+        Err(DeleteError::Referenced(tags)) => HttpResponse::Conflict().body(format!(
+            "Manifest encore référencé par: {}",
+            tags.join(", ")
+        )),
+        Err(DeleteError::Registry(e)) => error_response(e.context("Erreur")),
+    }
+}
@@ -1,35 +1,89 @@
 use actix_web::{delete, web, HttpResponse, Responder};
+use serde::Deserialize;
 
+use crate::audit::{audited, AuditAction};
+use crate::auth::{Caller, Role};
+use crate::deletion::{delete_version, validate_reference, DeleteError};
 use crate::entities::AppState;
+use crate::resilience::error_response;
+use crate::trash::{trash_version, TrashError};
+
+#[derive(Deserialize)]
+pub struct DeleteParams {
+    // Suppression définitive au lieu d'un passage en corbeille
+    #[serde(default)]
+    pub permanent: bool,
+    // Refuse la suppression définitive si un autre tag pointe sur le même manifest
+    #[serde(default)]
+    pub if_unreferenced: bool,
+}
 
 #[delete("/api/v1/{repository}/components/{reference}")]
 pub async fn delete_component(
     path: web::Path<(String, String)>,
+    params: web::Query<DeleteParams>,
     state: web::Data<AppState>,
+    caller: Caller,
 ) -> impl Responder {
     let (repository, reference) = path.into_inner();
-    let client = state.client.lock().unwrap();
-
-    let manifest_url = format!(
-        "{}/v2/{}/manifests/{}",
-        state.zot_config.url, repository, reference
-    );
+    if let Err(response) = caller.require(Role::Admin, &repository) {
+        return response;
+    }
+    if let Err(e) = validate_reference(&reference) {
+        return HttpResponse::BadRequest().body(e);
+    }
 
-    let response = client
-        .delete(&manifest_url)
-        .basic_auth(&state.zot_config.username, Some(&state.zot_config.password))
-        .send()
-        .await;
+    let action = if params.permanent {
+        AuditAction::Delete
+    } else {
+        AuditAction::Trash
+    };
+    audited(
+        &state,
+        &caller,
+        action,
+        &repository,
+        Some(&reference),
+        delete(&state, &caller, &repository, &reference, &params),
+    )
+    .await
+}
 
-    match response {
-        Ok(resp) if resp.status().is_success() => HttpResponse::Ok().body("Suppression réussie!"),
-        Ok(resp) => {
-            if resp.status() == reqwest::StatusCode::NOT_FOUND {
-                HttpResponse::NotFound().body("Composant non trouvé")
-            } else {
-                HttpResponse::BadRequest().body(format!("Erreur suppression: {}", resp.status()))
+async fn delete(
+    state: &AppState,
+    caller: &Caller,
+    repository: &str,
+    reference: &str,
+    params: &DeleteParams,
+) -> HttpResponse {
+    if !params.permanent {
+        return match trash_version(state, &caller.credentials, repository, reference).await {
+            Ok(entry) => HttpResponse::Ok().json(entry),
+            Err(TrashError::NotFound) => HttpResponse::NotFound().body("Composant non trouvé"),
+            Err(TrashError::AlreadyTrashed) => HttpResponse::Conflict()
+                .body("Une autre version de même référence est déjà en corbeille"),
+            Err(TrashError::Registry(e)) => error_response(e.context("Erreur")),
+            Err(TrashError::Conflict | TrashError::Expired) => {
+                HttpResponse::InternalServerError().body("Erreur: état de corbeille inattendu")
             }
-        }
-        Err(e) => HttpResponse::InternalServerError().body(format!("Erreur: {}", e)),
+        };
+    }
+
+    match delete_version(
+        state,
+        &caller.credentials,
+        repository,
+        reference,
+        params.if_unreferenced,
+    )
+    .await
+    {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(DeleteError::NotFound) => HttpResponse::NotFound().body("Composant non trouvé"),
+        Err(DeleteError::Referenced(tags)) => HttpResponse::Conflict().body(format!(
+            "Manifest encore référencé par: {}",
+            tags.join(", ")
+        )),
+        Err(DeleteError::Registry(e)) => error_response(e.context("Erreur")),
     }
 }
@@ -0,0 +1,37 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::auth::{Caller, Role};
+use crate::entities::AppState;
+
+#[derive(Deserialize)]
+pub struct AuditParams {
+    pub repository: Option<String>,
+    // Bornes incluses, au format RFC 3339
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[get("/api/v1/admin/audit")]
+pub async fn get_audit_log(
+    params: web::Query<AuditParams>,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    let authorized = match &params.repository {
+        Some(repository) => caller.require(Role::Admin, repository),
+        None => caller.require_global(Role::Admin),
+    };
+    if let Err(response) = authorized {
+        return response;
+    }
+
+    match state
+        .audit_log
+        .query(params.repository.as_deref(), params.from, params.to)
+    {
+        Ok(events) => HttpResponse::Ok().json(events),
+        Err(e) => HttpResponse::InternalServerError().body(e),
+    }
+}
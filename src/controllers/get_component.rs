@@ -2,43 +2,57 @@ use actix_web::{get, web, HttpResponse, Responder};
 use base64::engine::general_purpose::STANDARD as BASE64;
 use base64::Engine;
 
+use crate::auth::{Caller, Role};
+use crate::deletion::validate_reference;
 use crate::entities::{AppState, ComponentResponse, Manifest};
-use crate::services::calculate_sha256;
+use crate::metrics::METRICS;
+use crate::resilience::{error_response, ZotError};
+use crate::services::{calculate_sha256, send};
 
 #[get("/api/v1/{repository}/components/{reference}")]
 pub async fn get_component(
     path: web::Path<(String, String)>,
     state: web::Data<AppState>,
+    caller: Caller,
 ) -> impl Responder {
     let (repository, reference) = path.into_inner();
-    let client = state.client.lock().unwrap();
+    if let Err(response) = caller.require(Role::Reader, &repository) {
+        return response;
+    }
+    if let Err(e) = validate_reference(&reference) {
+        return HttpResponse::BadRequest().body(e);
+    }
+
+    let client = state.registry_client.lock().unwrap().clone();
 
     let manifest_url = format!(
         "{}/v2/{}/manifests/{}",
         state.zot_config.url, repository, reference
     );
-    let manifest_response = client
-        .get(&manifest_url)
-        .basic_auth(&state.zot_config.username, Some(&state.zot_config.password))
-        .header("Accept", "application/vnd.oci.image.manifest.v1+json")
-        .send()
-        .await;
+    let manifest_response = send(
+        "get_manifest",
+        caller
+            .credentials
+            .apply(client.get(&manifest_url))
+            .header("Accept", "application/vnd.oci.image.manifest.v1+json"),
+    )
+    .await;
 
     let manifest = match manifest_response {
-        Ok(resp) if resp.status().is_success() => match resp.json::<Manifest>().await {
-            Ok(m) => Some(m),
-            Err(e) => {
-                return HttpResponse::InternalServerError()
-                    .body(format!("Erreur parsing manifest: {}", e))
+        Ok(resp) if resp.status().is_success() => {
+            METRICS.downloaded(resp.content_length().unwrap_or(0) as usize);
+            match resp.json::<Manifest>().await {
+                Ok(m) => Some(m),
+                Err(e) => {
+                    return HttpResponse::InternalServerError()
+                        .body(format!("Erreur parsing manifest: {}", e))
+                }
             }
-        },
+        }
         Ok(resp) => {
             return HttpResponse::NotFound().body(format!("Manifest non trouvé: {}", resp.status()))
         }
-        Err(e) => {
-            return HttpResponse::InternalServerError()
-                .body(format!("Erreur récupération manifest: {}", e))
-        }
+        Err(e) => return error_response(ZotError::upstream("Erreur récupération manifest", e)),
     };
 
     let wasm_binary = if let Some(ref manifest) = manifest {
@@ -48,15 +62,15 @@ pub async fn get_component(
                     "{}/v2/{}/blobs/{}",
                     state.zot_config.url, repository, layer.digest
                 );
-                let wasm_response = client
-                    .get(&wasm_url)
-                    .basic_auth(&state.zot_config.username, Some(&state.zot_config.password))
-                    .send()
-                    .await;
+                let wasm_response =
+                    send("get_blob", caller.credentials.apply(client.get(&wasm_url))).await;
 
                 match wasm_response {
                     Ok(resp) if resp.status().is_success() => match resp.bytes().await {
-                        Ok(bytes) => Some(BASE64.encode(bytes)),
+                        Ok(bytes) => {
+                            METRICS.downloaded(bytes.len());
+                            Some(BASE64.encode(bytes))
+                        }
                         Err(e) => {
                             return HttpResponse::InternalServerError()
                                 .body(format!("Erreur lecture WASM: {}", e))
@@ -66,10 +80,7 @@ pub async fn get_component(
                         return HttpResponse::InternalServerError()
                             .body(format!("Erreur récupération WASM: {}", resp.status()))
                     }
-                    Err(e) => {
-                        return HttpResponse::InternalServerError()
-                            .body(format!("Erreur requête WASM: {}", e))
-                    }
+                    Err(e) => return error_response(ZotError::upstream("Erreur requête WASM", e)),
                 }
             } else {
                 None
@@ -86,15 +97,16 @@ pub async fn get_component(
             "{}/v2/{}/blobs/{}",
             state.zot_config.url, repository, manifest.config.digest
         );
-        let config_response = client
-            .get(&config_url)
-            .basic_auth(&state.zot_config.username, Some(&state.zot_config.password))
-            .send()
-            .await;
+        let config_response = send(
+            "get_blob",
+            caller.credentials.apply(client.get(&config_url)),
+        )
+        .await;
 
         match config_response {
             Ok(resp) if resp.status().is_success() => match resp.bytes().await {
                 Ok(bytes) => {
+                    METRICS.downloaded(bytes.len());
                     let calculated_digest = calculate_sha256(&bytes);
                     if calculated_digest != manifest.config.digest {
                         return HttpResponse::InternalServerError()
@@ -117,10 +129,7 @@ pub async fn get_component(
                 return HttpResponse::InternalServerError()
                     .body(format!("Erreur récupération config: {}", resp.status()))
             }
-            Err(e) => {
-                return HttpResponse::InternalServerError()
-                    .body(format!("Erreur requête config: {}", e))
-            }
+            Err(e) => return error_response(ZotError::upstream("Erreur requête config", e)),
         }
     } else {
         None
@@ -0,0 +1,62 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::auth::{Caller, Role};
+use crate::deletion::validate_reference;
+use crate::entities::AppState;
+use crate::history::{descriptor_of, history_entries, load_history, HistoryEntry};
+use crate::resilience::error_response;
+use crate::services::fetch_manifest_raw;
+
+#[derive(Serialize)]
+struct HistoryResponse {
+    reference: String,
+    current: Option<String>,
+    entries: Vec<HistoryEntry>,
+}
+
+#[get("/api/v1/{repository}/components/{reference}/history")]
+pub async fn get_component_history(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    let (repository, reference) = path.into_inner();
+    if let Err(response) = caller.require(Role::Reader, &repository) {
+        return response;
+    }
+    if let Err(e) = validate_reference(&reference) {
+        return HttpResponse::BadRequest().body(e);
+    }
+
+    let client = state.registry_client.lock().unwrap().clone();
+
+    let current = match fetch_manifest_raw(
+        &client,
+        &state.zot_config.url,
+        &repository,
+        &reference,
+        &caller.credentials,
+    )
+    .await
+    {
+        Ok(raw) => raw.map(|raw| descriptor_of(&raw).digest),
+        Err(e) => return error_response(e),
+    };
+
+    let descriptors = match load_history(&state, &caller.credentials, &repository, &reference).await
+    {
+        Ok(descriptors) => descriptors,
+        Err(e) => return error_response(e),
+    };
+
+    if current.is_none() && descriptors.is_empty() {
+        return HttpResponse::NotFound().body("Composant non trouvé");
+    }
+
+    HttpResponse::Ok().json(HistoryResponse {
+        entries: history_entries(descriptors, current.as_deref()),
+        reference,
+        current,
+    })
+}
@@ -0,0 +1,12 @@
+use actix_web::{get, web, HttpResponse, Responder};
+
+use crate::auth::{Caller, Role};
+use crate::entities::AppState;
+
+#[get("/api/v1/admin/gc")]
+pub async fn get_garbage_collection(state: web::Data<AppState>, caller: Caller) -> impl Responder {
+    if let Err(response) = caller.require_global(Role::Admin) {
+        return response;
+    }
+    HttpResponse::Ok().json(state.blob_ledger.status())
+}
@@ -0,0 +1,8 @@
+use actix_web::{get, HttpResponse, Responder};
+use serde_json::json;
+
+// Vivacité du processus : ne dépend pas de Zot
+#[get("/healthz")]
+pub async fn get_health() -> impl Responder {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
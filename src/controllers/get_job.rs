@@ -0,0 +1,21 @@
+use actix_web::{get, web, HttpResponse, Responder};
+
+use crate::auth::{Caller, Role};
+use crate::entities::AppState;
+
+// État d'une publication asynchrone : progression par blob, puis digest ou erreur
+#[get("/api/v1/jobs/{id}")]
+pub async fn get_job(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    let job = match state.job_store.get(&path.into_inner()) {
+        Some(job) => job,
+        None => return HttpResponse::NotFound().body("Tâche non trouvée"),
+    };
+    if let Err(response) = caller.require(Role::Reader, &job.repository) {
+        return response;
+    }
+    HttpResponse::Ok().json(job)
+}
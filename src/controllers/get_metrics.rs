@@ -0,0 +1,11 @@
+use actix_web::{get, HttpResponse, Responder};
+
+use crate::metrics::METRICS;
+
+// Non authentifié, comme attendu par un scraper Prometheus
+#[get("/metrics")]
+pub async fn get_metrics() -> impl Responder {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(METRICS.render())
+}
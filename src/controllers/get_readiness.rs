@@ -0,0 +1,15 @@
+use actix_web::{get, web, HttpResponse, Responder};
+
+use crate::entities::AppState;
+use crate::health::check_readiness;
+
+// Non authentifié, pour l'orchestrateur ; 503 si Zot est injoignable ou refuse les identifiants
+#[get("/readyz")]
+pub async fn get_readiness(state: web::Data<AppState>) -> impl Responder {
+    let readiness = check_readiness(&state).await;
+    if readiness.ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
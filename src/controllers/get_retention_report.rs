@@ -0,0 +1,40 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+use crate::auth::{Caller, Role};
+use crate::entities::AppState;
+use crate::resilience::error_response;
+use crate::retention::apply_retention;
+
+#[derive(Deserialize)]
+pub struct RetentionParams {
+    pub repository: Option<String>,
+}
+
+// Rapport à blanc : ce que les politiques de rétention supprimeraient
+#[get("/api/v1/admin/retention")]
+pub async fn get_retention_report(
+    params: web::Query<RetentionParams>,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    let authorized = match &params.repository {
+        Some(repository) => caller.require(Role::Admin, repository),
+        None => caller.require_global(Role::Admin),
+    };
+    if let Err(response) = authorized {
+        return response;
+    }
+
+    match apply_retention(
+        &state,
+        &caller.credentials,
+        params.repository.as_deref(),
+        true,
+    )
+    .await
+    {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => error_response(e.context("Erreur rétention")),
+    }
+}
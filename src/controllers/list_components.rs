@@ -0,0 +1,47 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::auth::{Caller, Role};
+use crate::deletion::is_internal_tag;
+use crate::entities::AppState;
+use crate::resilience::error_response;
+use crate::services::list_tags;
+
+#[derive(Serialize)]
+struct ComponentList {
+    repository: String,
+    versions: Vec<String>,
+}
+
+// Versions publiées ; les tags internes (historique, corbeille) sont exclus
+#[get("/api/v1/{repository}/components")]
+pub async fn list_components(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    let repository = path.into_inner();
+    if let Err(response) = caller.require(Role::Reader, &repository) {
+        return response;
+    }
+
+    let client = state.registry_client.lock().unwrap().clone();
+
+    match list_tags(
+        &client,
+        &state.zot_config.url,
+        &repository,
+        &caller.credentials,
+    )
+    .await
+    {
+        Ok(tags) => HttpResponse::Ok().json(ComponentList {
+            versions: tags
+                .into_iter()
+                .filter(|tag| !is_internal_tag(tag))
+                .collect(),
+            repository,
+        }),
+        Err(e) => error_response(e),
+    }
+}
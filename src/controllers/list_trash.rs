@@ -0,0 +1,23 @@
+use actix_web::{get, web, HttpResponse, Responder};
+
+use crate::auth::{Caller, Role};
+use crate::entities::AppState;
+use crate::resilience::error_response;
+use crate::trash::list_trash;
+
+#[get("/api/v1/{repository}/trash")]
+pub async fn list_trashed_components(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    let repository = path.into_inner();
+    if let Err(response) = caller.require(Role::Reader, &repository) {
+        return response;
+    }
+
+    match list_trash(&state, &caller.credentials, &repository).await {
+        Ok(entries) => HttpResponse::Ok().json(entries),
+        Err(e) => error_response(e),
+    }
+}
@@ -0,0 +1,29 @@
+use actix_web::{get, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+use crate::auth::{Caller, Role};
+use crate::entities::AppState;
+use crate::webhooks::DeliveryStatus;
+
+#[derive(Deserialize)]
+pub struct DeliveryParams {
+    pub status: Option<DeliveryStatus>,
+    pub endpoint: Option<String>,
+}
+
+#[get("/api/v1/admin/webhooks/deliveries")]
+pub async fn list_webhook_deliveries(
+    params: web::Query<DeliveryParams>,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    if let Err(response) = caller.require_global(Role::Admin) {
+        return response;
+    }
+
+    HttpResponse::Ok().json(
+        state
+            .webhook_queue
+            .deliveries(params.status, params.endpoint.as_deref()),
+    )
+}
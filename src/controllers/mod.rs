@@ -1,4 +1,22 @@
 pub mod push_component;
 pub mod get_component;
 pub mod update_component;
-pub mod delete_component;
\ No newline at end of file
+pub mod delete_component;
+pub mod patch_component;
+pub mod get_component_history;
+pub mod rollback_component;
+pub mod delete_all_components;
+pub mod list_components;
+pub mod list_trash;
+pub mod restore_component;
+pub mod get_garbage_collection;
+pub mod run_garbage_collection;
+pub mod get_retention_report;
+pub mod run_retention;
+pub mod get_audit_log;
+pub mod list_webhook_deliveries;
+pub mod stream_events;
+pub mod get_metrics;
+pub mod get_health;
+pub mod get_readiness;
+pub mod get_job;
\ No newline at end of file
@@ -0,0 +1,125 @@
+use actix_web::{patch, web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::audit::{audited, AuditAction};
+use crate::auth::{Caller, Role};
+use crate::deletion::validate_reference;
+use crate::entities::{AppState, ManifestMetadata, Operation};
+use crate::publish::{
+    creation_time, fetch_current_component, publish_component, ConfigSource, LayerSource,
+};
+use crate::resilience::error_response;
+use crate::services::apply_merge_patch;
+
+#[derive(Deserialize)]
+pub struct PatchParams {
+    // Date de création du manifest en secondes depuis l'epoch, pour un digest reproductible
+    pub source_date_epoch: Option<i64>,
+}
+
+// Mise à jour du CRD seul : document complet (application/json)
+// ou JSON Merge Patch (application/merge-patch+json), la couche WASM existante est conservée
+#[patch("/api/v1/{repository}/components/{reference}")]
+pub async fn patch_component(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    params: web::Query<PatchParams>,
+    body: web::Bytes,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    let (repository, reference) = path.into_inner();
+    if let Err(response) = caller.require(Role::Publisher, &repository) {
+        return response;
+    }
+    if let Err(e) = validate_reference(&reference) {
+        return HttpResponse::BadRequest().body(e);
+    }
+    let created = match creation_time(params.source_date_epoch) {
+        Ok(created) => created,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
+
+    let is_merge_patch = req
+        .headers()
+        .get("Content-Type")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/merge-patch+json"));
+
+    audited(
+        &state,
+        &caller,
+        AuditAction::Patch,
+        &repository,
+        Some(&reference),
+        patch(
+            &state,
+            &caller,
+            &repository,
+            &reference,
+            &body,
+            is_merge_patch,
+            created,
+        ),
+    )
+    .await
+}
+
+async fn patch(
+    state: &AppState,
+    caller: &Caller,
+    repository: &str,
+    reference: &str,
+    body: &[u8],
+    is_merge_patch: bool,
+    created: DateTime<Utc>,
+) -> HttpResponse {
+    let document: Value = match serde_json::from_slice(body) {
+        Ok(document) => document,
+        Err(e) => return HttpResponse::BadRequest().body(format!("Erreur JSON: {}", e)),
+    };
+
+    let current =
+        match fetch_current_component(state, &caller.credentials, repository, reference).await {
+            Ok(Some(current)) => current,
+            Ok(None) => return HttpResponse::NotFound().body("Composant non trouvé"),
+            Err(e) => return error_response(e),
+        };
+
+    let crd = if is_merge_patch {
+        let mut crd = current.crd;
+        apply_merge_patch(&mut crd, &document);
+        crd
+    } else {
+        document
+    };
+
+    let metadata: ManifestMetadata = match serde_json::from_value(crd) {
+        Ok(metadata) => metadata,
+        Err(e) => return HttpResponse::BadRequest().body(format!("Erreur manifest: {}", e)),
+    };
+
+    if metadata.metadata.name != repository || metadata.metadata.annotations.version != reference {
+        return HttpResponse::BadRequest()
+            .body("Le repository ou la référence ne correspond pas au manifest");
+    }
+
+    match publish_component(
+        state,
+        &caller.credentials,
+        repository,
+        reference,
+        &metadata,
+        ConfigSource::Upload,
+        LayerSource::Existing(current.manifest.layers),
+        Operation::Patch,
+        created,
+    )
+    .await
+    {
+        Ok(_) => HttpResponse::Ok().body("Mise à jour réussie!"),
+        Err(e) => error_response(e),
+    }
+}
@@ -0,0 +1,50 @@
+use actix_web::{post, web, HttpResponse, Responder};
+
+use crate::audit::{audited, AuditAction};
+use crate::auth::{Caller, Role};
+use crate::deletion::validate_reference;
+use crate::entities::AppState;
+use crate::resilience::error_response;
+use crate::trash::{restore_version, TrashError};
+
+#[post("/api/v1/{repository}/trash/{reference}/restore")]
+pub async fn restore_component(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    let (repository, reference) = path.into_inner();
+    if let Err(response) = caller.require(Role::Admin, &repository) {
+        return response;
+    }
+    if let Err(e) = validate_reference(&reference) {
+        return HttpResponse::BadRequest().body(e);
+    }
+
+    audited(
+        &state,
+        &caller,
+        AuditAction::Restore,
+        &repository,
+        Some(&reference),
+        async {
+            match restore_version(&state, &caller.credentials, &repository, &reference).await {
+                Ok(descriptor) => HttpResponse::Ok().json(descriptor),
+                Err(TrashError::NotFound) => {
+                    HttpResponse::NotFound().body("Composant absent de la corbeille")
+                }
+                Err(TrashError::Conflict) => {
+                    HttpResponse::Conflict().body("Une version portant cette référence existe déjà")
+                }
+                Err(TrashError::Expired) => {
+                    HttpResponse::Gone().body("Délai de restauration dépassé")
+                }
+                Err(TrashError::AlreadyTrashed) => {
+                    HttpResponse::InternalServerError().body("Erreur: état de corbeille inattendu")
+                }
+                Err(TrashError::Registry(e)) => error_response(e.context("Erreur")),
+            }
+        },
+    )
+    .await
+}
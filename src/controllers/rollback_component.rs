@@ -0,0 +1,88 @@
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+use crate::audit::{audited, AuditAction};
+use crate::auth::{Caller, Role};
+use crate::deletion::validate_reference;
+use crate::entities::{AppState, Operation};
+use crate::history::{descriptor_of, load_history, tag_manifest};
+use crate::resilience::error_response;
+use crate::services::fetch_manifest_raw;
+
+#[derive(Deserialize)]
+pub struct RollbackRequest {
+    pub digest: String,
+}
+
+#[post("/api/v1/{repository}/components/{reference}/rollback")]
+pub async fn rollback_component(
+    path: web::Path<(String, String)>,
+    body: web::Json<RollbackRequest>,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    let (repository, reference) = path.into_inner();
+    if let Err(response) = caller.require(Role::Publisher, &repository) {
+        return response;
+    }
+    if let Err(e) = validate_reference(&reference) {
+        return HttpResponse::BadRequest().body(e);
+    }
+
+    let target = body.into_inner().digest;
+
+    audited(
+        &state,
+        &caller,
+        AuditAction::Rollback,
+        &repository,
+        Some(&reference),
+        rollback(&state, &caller, &repository, &reference, &target),
+    )
+    .await
+}
+
+async fn rollback(
+    state: &AppState,
+    caller: &Caller,
+    repository: &str,
+    reference: &str,
+    target: &str,
+) -> HttpResponse {
+    match load_history(state, &caller.credentials, repository, reference).await {
+        Ok(entries) if entries.iter().any(|entry| entry.digest == target) => {}
+        Ok(_) => return HttpResponse::NotFound().body("Digest absent de l'historique du tag"),
+        Err(e) => return error_response(e),
+    }
+
+    let client = state.registry_client.lock().unwrap().clone();
+    let raw = match fetch_manifest_raw(
+        &client,
+        &state.zot_config.url,
+        repository,
+        target,
+        &caller.credentials,
+    )
+    .await
+    {
+        Ok(Some(raw)) => raw,
+        Ok(None) => return HttpResponse::NotFound().body("Manifest non trouvé dans le registre"),
+        Err(e) => return error_response(e),
+    };
+
+    let media_type = descriptor_of(&raw).media_type;
+    match tag_manifest(
+        state,
+        &caller.credentials,
+        repository,
+        reference,
+        &media_type,
+        raw,
+        Operation::Rollback,
+    )
+    .await
+    {
+        Ok(descriptor) => HttpResponse::Ok().json(descriptor),
+        Err(e) => error_response(e),
+    }
+}
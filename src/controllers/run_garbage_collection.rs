@@ -0,0 +1,42 @@
+use actix_web::{post, web, HttpResponse, Responder};
+use serde::Deserialize;
+
+use crate::auth::{Caller, Role};
+use crate::entities::AppState;
+use crate::gc::collect_garbage;
+use crate::resilience::error_response;
+
+#[derive(Deserialize)]
+pub struct GcParams {
+    pub repository: Option<String>,
+    // Rapport des blobs qui seraient supprimés, sans rien supprimer
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[post("/api/v1/admin/gc")]
+pub async fn run_garbage_collection(
+    params: web::Query<GcParams>,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    let authorized = match &params.repository {
+        Some(repository) => caller.require(Role::Admin, repository),
+        None => caller.require_global(Role::Admin),
+    };
+    if let Err(response) = authorized {
+        return response;
+    }
+
+    match collect_garbage(
+        &state,
+        &caller.credentials,
+        params.repository.as_deref(),
+        params.dry_run,
+    )
+    .await
+    {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => error_response(e.context("Erreur GC")),
+    }
+}
@@ -0,0 +1,34 @@
+use actix_web::{post, web, HttpResponse, Responder};
+
+use crate::auth::{Caller, Role};
+use crate::controllers::get_retention_report::RetentionParams;
+use crate::entities::AppState;
+use crate::resilience::error_response;
+use crate::retention::apply_retention;
+
+#[post("/api/v1/admin/retention")]
+pub async fn run_retention(
+    params: web::Query<RetentionParams>,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    let authorized = match &params.repository {
+        Some(repository) => caller.require(Role::Admin, repository),
+        None => caller.require_global(Role::Admin),
+    };
+    if let Err(response) = authorized {
+        return response;
+    }
+
+    match apply_retention(
+        &state,
+        &caller.credentials,
+        params.repository.as_deref(),
+        false,
+    )
+    .await
+    {
+        Ok(report) => HttpResponse::Ok().json(report),
+        Err(e) => error_response(e.context("Erreur rétention")),
+    }
+}
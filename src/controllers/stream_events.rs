@@ -0,0 +1,126 @@
+use actix_web::{get, web, HttpRequest, HttpResponse, Responder};
+use futures::stream;
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::auth::{Caller, Role};
+use crate::entities::AppState;
+use crate::events::StreamEvent;
+use crate::services::glob_match;
+
+#[derive(Deserialize)]
+pub struct EventParams {
+    // Motif glob sur le nom du dépôt
+    pub repository: Option<String>,
+    pub component_type: Option<String>,
+}
+
+struct Subscription {
+    state: web::Data<AppState>,
+    caller: Caller,
+    params: EventParams,
+    receiver: watch::Receiver<u64>,
+    last_id: u64,
+    pending: VecDeque<web::Bytes>,
+}
+
+impl Subscription {
+    fn accepts(&self, event: &StreamEvent) -> bool {
+        let payload = &event.payload;
+        self.caller.can(Role::Reader, &payload.repository)
+            && self
+                .params
+                .repository
+                .as_ref()
+                .is_none_or(|pattern| glob_match(pattern, &payload.repository))
+            && self
+                .params
+                .component_type
+                .as_ref()
+                .is_none_or(|component_type| {
+                    payload.component_type.as_ref() == Some(component_type)
+                })
+    }
+}
+
+fn format_event(event: &StreamEvent) -> web::Bytes {
+    let data = serde_json::to_string(&event.payload).expect("événement toujours sérialisable");
+    web::Bytes::from(format!(
+        "id: {}\nevent: {}\ndata: {}\n\n",
+        event.id,
+        event.payload.event.as_str(),
+        data
+    ))
+}
+
+// Flux SSE des push, mises à jour et suppressions ; l'en-tête Last-Event-ID rejoue les
+// événements manqués encore présents dans le tampon
+#[get("/api/v1/events")]
+pub async fn stream_events(
+    req: HttpRequest,
+    params: web::Query<EventParams>,
+    state: web::Data<AppState>,
+    caller: Caller,
+) -> impl Responder {
+    if let Some(repository) = &params.repository {
+        if let Err(response) = caller.require(Role::Reader, repository) {
+            return response;
+        }
+    }
+
+    let receiver = state.event_bus.subscribe();
+    let last_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or_else(|| state.event_bus.latest_id());
+    let keep_alive = Duration::from_secs(state.events_config.keep_alive_seconds.max(1));
+
+    let subscription = Subscription {
+        state: state.clone(),
+        caller,
+        params: params.into_inner(),
+        receiver,
+        last_id,
+        pending: VecDeque::new(),
+    };
+
+    let events = stream::unfold(subscription, move |mut subscription| async move {
+        loop {
+            if let Some(chunk) = subscription.pending.pop_front() {
+                return Some((Ok::<_, actix_web::Error>(chunk), subscription));
+            }
+
+            subscription.receiver.borrow_and_update();
+            for event in subscription.state.event_bus.since(subscription.last_id) {
+                subscription.last_id = event.id;
+                if subscription.accepts(&event) {
+                    subscription.pending.push_back(format_event(&event));
+                }
+            }
+            if !subscription.pending.is_empty() {
+                continue;
+            }
+
+            match tokio::time::timeout(keep_alive, subscription.receiver.changed()).await {
+                Ok(Ok(())) => continue,
+                Ok(Err(_)) => return None,
+                // Commentaire SSE pour garder la connexion ouverte à travers les proxys
+                Err(_) => {
+                    return Some((
+                        Ok(web::Bytes::from_static(b": keep-alive\n\n")),
+                        subscription,
+                    ))
+                }
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events)
+}
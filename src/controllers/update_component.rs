@@ -1,217 +1,176 @@
-use actix_multipart::Multipart;
-use actix_web::{put, web, HttpResponse, Responder};
-use chrono::Utc;
-use futures::StreamExt;
-use serde_json::Value;
-use std::fs;
-
-use crate::entities::{AppState, Config, Layer, Manifest, ManifestMetadata};
-use crate::services::{calculate_sha256, init_upload, upload_blob};
+use actix_web::{put, web, HttpRequest, HttpResponse, Responder};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::audit::{audited, AuditAction};
+use crate::auth::{Caller, Role};
+use crate::deletion::validate_reference;
+use crate::entities::{AppState, ManifestMetadata, Operation, RegistryCredentials};
+use crate::idempotency::idempotent;
+use crate::publish::{
+    creation_time, dry_run_component, fetch_current_component, plan_component, publish_component,
+    ConfigSource, LayerSource,
+};
+use crate::resilience::error_response;
+use crate::upload::{read_component_upload, ComponentUpload};
+
+#[derive(Deserialize)]
+pub struct UpdateParams {
+    // Valide la mise à jour et décrit le manifest qui serait publié, sans rien écrire
+    #[serde(default)]
+    pub dry_run: bool,
+    // Date de création du manifest en secondes depuis l'epoch, pour un digest reproductible
+    pub source_date_epoch: Option<i64>,
+}
 
+// Le CRD et le binaire sont chacun optionnels : la partie absente est reprise du manifest courant
 #[put("/api/v1/{repository}/components/{reference}")]
 pub async fn update_component(
     path: web::Path<(String, String)>,
-    mut payload: Multipart,
+    params: web::Query<UpdateParams>,
+    req: HttpRequest,
+    payload: web::Payload,
     state: web::Data<AppState>,
+    caller: Caller,
 ) -> impl Responder {
     let (repository, reference) = path.into_inner();
-    let client = state.client.lock().unwrap();
+    if let Err(response) = caller.require(Role::Publisher, &repository) {
+        return response;
+    }
+    if let Err(e) = validate_reference(&reference) {
+        return HttpResponse::BadRequest().body(e);
+    }
+    let created = match creation_time(params.source_date_epoch) {
+        Ok(created) => created,
+        Err(e) => return HttpResponse::BadRequest().body(e),
+    };
 
-    let mut manifest: Option<ManifestMetadata> = None;
-    let mut wasm_file: Option<(String, Vec<u8>)> = None;
+    let upload = match read_component_upload(&req, payload, &state).await {
+        Ok(upload) => upload,
+        Err(response) => return response,
+    };
+    if params.dry_run {
+        return update(
+            &state,
+            &caller.credentials,
+            &repository,
+            &reference,
+            &upload,
+            created,
+            true,
+        )
+        .await;
+    }
 
-    while let Some(item) = payload.next().await {
-        let mut field = match item {
-            Ok(field) => field,
-            Err(e) => return HttpResponse::BadRequest().body(format!("Erreur multipart: {}", e)),
-        };
+    idempotent(
+        &state.idempotency_store,
+        &req,
+        &caller,
+        &upload.fingerprint(),
+        audited(
+            &state,
+            &caller,
+            AuditAction::Update,
+            &repository,
+            Some(&reference),
+            update(
+                &state,
+                &caller.credentials,
+                &repository,
+                &reference,
+                &upload,
+                created,
+                false,
+            ),
+        ),
+    )
+    .await
+}
 
-        let filename = match field.content_disposition().get_filename() {
-            Some(name) => name.to_string(),
-            None => continue,
-        };
+async fn update(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    reference: &str,
+    upload: &ComponentUpload,
+    created: DateTime<Utc>,
+    dry_run: bool,
+) -> HttpResponse {
+    let wasm = upload.wasm.as_ref();
+    if upload.manifest.is_none() && wasm.is_none() {
+        return HttpResponse::BadRequest().body("Manifest.json ou fichier .wasm requis");
+    }
+    // Les pièces jointes sont publiées à la suite du binaire : elles l'accompagnent toujours
+    if wasm.is_none() && !upload.attachments.is_empty() {
+        return HttpResponse::BadRequest().body("Pièces jointes sans fichier .wasm");
+    }
 
-        let mut content = Vec::new();
-        while let Some(chunk) = field.next().await {
-            content.extend_from_slice(&chunk.unwrap());
+    let current = if upload.manifest.is_none() || wasm.is_none() {
+        match fetch_current_component(state, credentials, repository, reference).await {
+            Ok(Some(current)) => Some(current),
+            Ok(None) => return HttpResponse::NotFound().body("Composant non trouvé"),
+            Err(e) => return error_response(e),
         }
+    } else {
+        None
+    };
 
-        if filename.ends_with(".json") {
-            manifest = match serde_json::from_slice(&content) {
-                Ok(m) => Some(m),
+    let stored_metadata: ManifestMetadata;
+    let (metadata, config) = match (&upload.manifest, &current) {
+        (Some(manifest), _) => (manifest, ConfigSource::Upload),
+        (None, Some(current)) => {
+            stored_metadata = match serde_json::from_value(current.crd.clone()) {
+                Ok(stored) => stored,
                 Err(e) => {
-                    return HttpResponse::BadRequest().body(format!("Erreur manifest: {}", e))
+                    return HttpResponse::InternalServerError()
+                        .body(format!("Erreur parsing config JSON: {}", e))
                 }
             };
-        } else if filename.ends_with(".wasm") {
-            let path = format!("/tmp/{}", filename);
-            fs::write(&path, &content).unwrap();
-            wasm_file = Some((path, content));
+            (
+                &stored_metadata,
+                ConfigSource::Existing(current.manifest.config.clone()),
+            )
         }
-    }
-
-    let manifest = match manifest {
-        Some(m) => m,
-        None => return HttpResponse::BadRequest().body("Manifest.json manquant"),
-    };
-    let (wasm_path, wasm_content) = match wasm_file {
-        Some(f) => f,
-        None => return HttpResponse::BadRequest().body("Fichier .wasm manquant"),
+        (None, None) => unreachable!("le composant courant est chargé quand le CRD manque"),
     };
 
-    if manifest.metadata.name != repository || manifest.metadata.annotations.version != reference {
+    if metadata.metadata.name != repository || metadata.metadata.annotations.version != reference {
         return HttpResponse::BadRequest()
             .body("Le repository ou la référence ne correspond pas au manifest");
     }
 
-    let layer_digest = calculate_sha256(&wasm_content);
-    let config_content = serde_json::to_vec(&manifest).unwrap();
-    let config_digest = calculate_sha256(&config_content);
-
-    let layer_upload_url = match init_upload(
-        &client,
-        &state.zot_config.url,
-        &repository,
-        &state.zot_config.username,
-        &state.zot_config.password,
-    )
-    .await
-    {
-        Ok(url) => url,
-        Err(e) => return HttpResponse::InternalServerError().body(e),
+    let layers = match (wasm, current) {
+        (Some(wasm), _) => LayerSource::Spooled(wasm, &upload.attachments),
+        (None, Some(current)) => LayerSource::Existing(current.manifest.layers),
+        (None, None) => unreachable!("le composant courant est chargé quand le binaire manque"),
     };
 
-    if let Err(e) = upload_blob(
-        &client,
-        &layer_upload_url,
-        &state.zot_config.username,
-        &state.zot_config.password,
-        &wasm_content,
-        &layer_digest,
-    )
-    .await
-    {
-        return HttpResponse::InternalServerError().body(e);
+    if dry_run {
+        return match plan_component(metadata, config, layers, created) {
+            Ok(plan) => {
+                match dry_run_component(state, credentials, repository, reference, plan).await {
+                    Ok(report) => HttpResponse::Ok().json(report),
+                    Err(e) => error_response(e),
+                }
+            }
+            Err(e) => HttpResponse::InternalServerError().body(e),
+        };
     }
 
-    let config_upload_url = match init_upload(
-        &client,
-        &state.zot_config.url,
-        &repository,
-        &state.zot_config.username,
-        &state.zot_config.password,
-    )
-    .await
-    {
-        Ok(url) => url,
-        Err(e) => return HttpResponse::InternalServerError().body(e),
-    };
-
-    if let Err(e) = upload_blob(
-        &client,
-        &config_upload_url,
-        &state.zot_config.username,
-        &state.zot_config.password,
-        &config_content,
-        &config_digest,
+    match publish_component(
+        state,
+        credentials,
+        repository,
+        reference,
+        metadata,
+        config,
+        layers,
+        Operation::Update,
+        created,
     )
     .await
     {
-        return HttpResponse::InternalServerError().body(e);
-    }
-
-    let mut annotations: serde_json::Map<String, Value> = serde_json::Map::new();
-    annotations.insert(
-        "org.opencontainers.image.title".to_string(),
-        Value::String(manifest.metadata.name.clone()),
-    );
-    annotations.insert(
-        "org.opencontainers.image.description".to_string(),
-        Value::String(manifest.metadata.annotations.description.clone()),
-    );
-    annotations.insert(
-        "org.opencontainers.image.version".to_string(),
-        Value::String(manifest.metadata.annotations.version.clone()),
-    );
-    annotations.insert(
-        "org.opencontainers.image.created".to_string(),
-        Value::String(Utc::now().to_rfc3339()),
-    );
-    annotations.insert(
-        "org.opencontainers.image.architecture".to_string(),
-        Value::String("wasm".to_string()),
-    );
-    annotations.insert(
-        "org.opencontainers.image.os".to_string(),
-        Value::String("any".to_string()),
-    );
-    if let Some(label) = &manifest.metadata.annotations.label {
-        annotations.insert(
-            "org.opencontainers.image.label".to_string(),
-            Value::String(label.clone()),
-        );
-    }
-    if let Some(icon) = &manifest.metadata.annotations.icon {
-        annotations.insert(
-            "org.opencontainers.image.icon".to_string(),
-            Value::String(icon.clone()),
-        );
-    }
-    if let Some(color) = &manifest.metadata.annotations.color {
-        annotations.insert(
-            "org.opencontainers.image.color".to_string(),
-            Value::String(color.clone()),
-        );
-    }
-    if let Some(ui) = &manifest.metadata.annotations.ui {
-        annotations.insert(
-            "org.opencontainers.image.ui".to_string(),
-            Value::String(ui.clone()),
-        );
-    }
-    annotations.insert(
-        "com.aneocorp.component.type".to_string(),
-        Value::String(manifest.spec.type_field.clone()),
-    );
-
-    let manifest_data = Manifest {
-        schema_version: 2,
-        media_type: "application/vnd.oci.image.manifest.v1+json".to_string(),
-        config: Config {
-            media_type: "application/vnd.wasm.config.v0+json".to_string(),
-            size: config_content.len() as i64,
-            digest: config_digest,
-        },
-        layers: vec![Layer {
-            media_type: "application/wasm".to_string(),
-            size: wasm_content.len() as i64,
-            digest: layer_digest,
-        }],
-        annotations: Some(Value::Object(annotations)),
-    };
-
-    let manifest_url = format!(
-        "{}/v2/{}/manifests/{}",
-        state.zot_config.url, repository, reference
-    );
-
-    let response = client
-        .put(&manifest_url)
-        .basic_auth(&state.zot_config.username, Some(&state.zot_config.password))
-        .header("Content-Type", "application/vnd.oci.image.manifest.v1+json")
-        .json(&manifest_data)
-        .send()
-        .await;
-
-    if !wasm_path.is_empty() {
-        fs::remove_file(&wasm_path).unwrap();
-    }
-
-    match response {
-        Ok(resp) if resp.status().is_success() => HttpResponse::Ok().body("Mise à jour réussie!"),
-        Ok(resp) => {
-            HttpResponse::BadRequest().body(format!("Erreur mise à jour: {}", resp.status()))
-        }
-        Err(e) => HttpResponse::InternalServerError().body(format!("Erreur: {}", e)),
+        Ok(_) => HttpResponse::Ok().body("Mise à jour réussie!"),
+        Err(e) => error_response(e),
     }
 }
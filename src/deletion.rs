@@ -0,0 +1,268 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+use crate::entities::{AppState, RegistryCredentials};
+use crate::history::{descriptor_of, history_tag, HISTORY_TAG_PREFIX};
+use crate::manifest_builder::INDEX_MEDIA_TYPE;
+use crate::resilience::ZotError;
+use crate::services::{delete_blob, delete_manifest, fetch_manifest_raw, list_tags};
+use crate::trash::TRASH_TAG_PREFIX;
+
+// Longueur maximale d'un tag OCI
+const MAX_TAG_LENGTH: usize = 128;
+
+// Les tags internes (historique, corbeille...) commencent par `_` et ne sont pas des versions
+pub fn is_internal_tag(tag: &str) -> bool {
+    tag.starts_with('_')
+}
+
+// Référence acceptée par l'API : jamais un tag interne, que l'appelant pourrait sinon écraser
+// ou lire, et assez courte pour que ses index `_history.` et `_trash.` restent des tags valides.
+// Un digest n'a ni historique ni corbeille
+pub fn validate_reference(reference: &str) -> Result<(), String> {
+    if is_internal_tag(reference) {
+        return Err(format!("Référence réservée: {}", reference));
+    }
+    let prefix = HISTORY_TAG_PREFIX.len().max(TRASH_TAG_PREFIX.len());
+    if !reference.contains(':') && prefix + reference.len() > MAX_TAG_LENGTH {
+        return Err(format!(
+            "Référence trop longue: {} caractères au plus",
+            MAX_TAG_LENGTH - prefix
+        ));
+    }
+    Ok(())
+}
+
+pub struct TaggedManifest {
+    pub tag: String,
+    pub digest: String,
+    pub raw: Vec<u8>,
+}
+
+#[derive(Serialize, Default)]
+pub struct DeleteReport {
+    pub tags: Vec<String>,
+    pub manifests: Vec<String>,
+    pub blobs: Vec<String>,
+}
+
+pub enum DeleteError {
+    NotFound,
+    // Autres tags pointant sur le même manifest
+    Referenced(Vec<String>),
+    Registry(ZotError),
+}
+
+impl From<ZotError> for DeleteError {
+    fn from(e: ZotError) -> Self {
+        DeleteError::Registry(e)
+    }
+}
+
+#[derive(Default)]
+pub struct Reachable {
+    // digest -> true si le manifest est un index
+    pub manifests: HashMap<String, bool>,
+    pub blobs: HashSet<String>,
+}
+
+pub async fn tagged_manifests(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+) -> Result<Vec<TaggedManifest>, ZotError> {
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+
+    let mut tagged = Vec::new();
+    for tag in list_tags(&client, &zot.url, repository, credentials).await? {
+        let raw = fetch_manifest_raw(&client, &zot.url, repository, &tag, credentials).await?;
+        if let Some(raw) = raw {
+            tagged.push(TaggedManifest {
+                digest: descriptor_of(&raw).digest,
+                tag,
+                raw,
+            });
+        }
+    }
+    Ok(tagged)
+}
+
+// Blobs (config et couches) et manifests enfants référencés par un manifest ou un index
+fn references(raw: &[u8]) -> (Vec<String>, Vec<String>) {
+    let value: Value = serde_json::from_slice(raw).unwrap_or_default();
+    let digests = |key: &str| -> Vec<String> {
+        value
+            .get(key)
+            .and_then(|items| items.as_array())
+            .map(|items| {
+                items
+                    .iter()
+                    .filter_map(|item| item.get("digest")?.as_str().map(|d| d.to_string()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let mut blobs = digests("layers");
+    if let Some(config) = value.pointer("/config/digest").and_then(|d| d.as_str()) {
+        blobs.push(config.to_string());
+    }
+    (blobs, digests("manifests"))
+}
+
+// Parcourt les manifests atteignables depuis les racines, y compris à travers les index
+pub async fn reachable_from(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    roots: &[&[u8]],
+) -> Result<Reachable, ZotError> {
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+
+    let mut reachable = Reachable::default();
+    let mut pending: Vec<Vec<u8>> = roots.iter().map(|raw| raw.to_vec()).collect();
+
+    while let Some(raw) = pending.pop() {
+        let descriptor = descriptor_of(&raw);
+        if reachable.manifests.contains_key(&descriptor.digest) {
+            continue;
+        }
+        reachable
+            .manifests
+            .insert(descriptor.digest, descriptor.media_type == INDEX_MEDIA_TYPE);
+
+        let (blobs, children) = references(&raw);
+        reachable.blobs.extend(blobs);
+        for child in children {
+            if reachable.manifests.contains_key(&child) {
+                continue;
+            }
+            let raw =
+                fetch_manifest_raw(&client, &zot.url, repository, &child, credentials).await?;
+            if let Some(raw) = raw {
+                pending.push(raw);
+            }
+        }
+    }
+    Ok(reachable)
+}
+
+// Supprime les tags donnés, puis les manifests et blobs qui ne sont plus atteignables
+// depuis les tags restants
+async fn remove_tags(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    tagged: Vec<TaggedManifest>,
+    removed_tags: &HashSet<String>,
+) -> Result<DeleteReport, ZotError> {
+    let (removed, kept): (Vec<_>, Vec<_>) = tagged
+        .into_iter()
+        .partition(|manifest| removed_tags.contains(&manifest.tag));
+
+    let removed_roots: Vec<&[u8]> = removed.iter().map(|m| m.raw.as_slice()).collect();
+    let kept_roots: Vec<&[u8]> = kept.iter().map(|m| m.raw.as_slice()).collect();
+    let candidates = reachable_from(state, credentials, repository, &removed_roots).await?;
+    let retained = reachable_from(state, credentials, repository, &kept_roots).await?;
+
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+    let mut report = DeleteReport::default();
+
+    // Les index d'abord, pour ne jamais laisser un index pointer sur un manifest supprimé
+    let mut manifests: Vec<(String, bool)> = candidates
+        .manifests
+        .into_iter()
+        .filter(|(digest, _)| !retained.manifests.contains_key(digest))
+        .collect();
+    manifests.sort_by_key(|(digest, is_index)| (!is_index, digest.clone()));
+
+    for (digest, _) in manifests {
+        if delete_manifest(&client, &zot.url, repository, &digest, credentials).await? {
+            report.manifests.push(digest);
+        }
+    }
+
+    // Manifest encore référencé ailleurs (autre historique...) : on retire seulement le tag
+    for manifest in &removed {
+        if retained.manifests.contains_key(&manifest.digest) {
+            delete_manifest(&client, &zot.url, repository, &manifest.tag, credentials).await?;
+        }
+    }
+    report.tags = removed.into_iter().map(|manifest| manifest.tag).collect();
+    report.tags.sort();
+
+    let mut blobs: Vec<String> = candidates
+        .blobs
+        .difference(&retained.blobs)
+        .cloned()
+        .collect();
+    blobs.sort();
+    for digest in blobs {
+        if delete_blob(&client, &zot.url, repository, &digest, credentials).await? {
+            report.blobs.push(digest);
+        }
+    }
+
+    Ok(report)
+}
+
+pub async fn delete_tags(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    tags: &HashSet<String>,
+) -> Result<DeleteReport, ZotError> {
+    let tagged = tagged_manifests(state, credentials, repository).await?;
+    remove_tags(state, credentials, repository, tagged, tags).await
+}
+
+// Supprime le manifest d'une version : tous les tags qui pointent dessus disparaissent avec lui
+pub async fn delete_version(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    reference: &str,
+    only_if_unreferenced: bool,
+) -> Result<DeleteReport, DeleteError> {
+    let tagged = tagged_manifests(state, credentials, repository).await?;
+
+    let digest = match tagged.iter().find(|m| m.tag == reference) {
+        Some(manifest) => manifest.digest.clone(),
+        None => return Err(DeleteError::NotFound),
+    };
+
+    let shared: Vec<String> = tagged
+        .iter()
+        .filter(|m| m.digest == digest && m.tag != reference && !is_internal_tag(&m.tag))
+        .map(|m| m.tag.clone())
+        .collect();
+    if only_if_unreferenced && !shared.is_empty() {
+        return Err(DeleteError::Referenced(shared));
+    }
+
+    let mut removed_tags = HashSet::new();
+    for tag in shared.into_iter().chain([reference.to_string()]) {
+        removed_tags.insert(history_tag(&tag));
+        removed_tags.insert(tag);
+    }
+
+    Ok(remove_tags(state, credentials, repository, tagged, &removed_tags).await?)
+}
+
+pub async fn delete_all_versions(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+) -> Result<DeleteReport, DeleteError> {
+    let tagged = tagged_manifests(state, credentials, repository).await?;
+    if tagged.is_empty() {
+        return Err(DeleteError::NotFound);
+    }
+
+    let removed_tags = tagged.iter().map(|m| m.tag.clone()).collect();
+    Ok(remove_tags(state, credentials, repository, tagged, &removed_tags).await?)
+}
@@ -1,8 +1,21 @@
 use std::sync::Mutex;
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::audit::AuditLog;
+use crate::config::{
+    AuthConfig, EventsConfig, GcConfig, HealthConfig, HistoryConfig, JobsConfig,
+    RetentionConfig, TrashConfig, UploadConfig, WebhookConfig,
+};
+use crate::events::EventBus;
+use crate::gc::BlobLedger;
+use crate::health::HealthCache;
+use crate::idempotency::IdempotencyStore;
+use crate::jobs::JobStore;
+use crate::jwt::JwksCache;
+use crate::webhooks::WebhookQueue;
+
 #[derive(Serialize, Deserialize)]
 pub struct Manifest {
     #[serde(rename = "schemaVersion")]
@@ -14,7 +27,7 @@ pub struct Manifest {
     pub annotations: Option<Value>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
     #[serde(rename = "mediaType")]
     pub media_type: String,
@@ -22,12 +35,57 @@ pub struct Config {
     pub digest: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Layer {
     #[serde(rename = "mediaType")]
     pub media_type: String,
     pub size: i64,
     pub digest: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<serde_json::Map<String, Value>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Descriptor {
+    #[serde(rename = "mediaType")]
+    pub media_type: String,
+    pub size: i64,
+    pub digest: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<serde_json::Map<String, Value>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ImageIndex {
+    #[serde(rename = "schemaVersion")]
+    pub schema_version: i32,
+    #[serde(rename = "mediaType")]
+    pub media_type: String,
+    pub manifests: Vec<Descriptor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<serde_json::Map<String, Value>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    Push,
+    Update,
+    Patch,
+    Rollback,
+    Restore,
+}
+
+impl Operation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Push => "push",
+            Operation::Update => "update",
+            Operation::Patch => "patch",
+            Operation::Rollback => "rollback",
+            Operation::Restore => "restore",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize)]
@@ -89,13 +147,83 @@ pub struct ComponentResponse {
    
 }
 
+#[derive(Deserialize)]
+#[serde(default)]
 pub struct ZotConfig {
     pub url: String,
     pub username: String,
     pub password: String,
+    // Transmet à Zot les identifiants de l'appelant ; le compte partagé ne sert plus
+    // qu'aux tâches de fond
+    pub passthrough: bool,
+    // En passthrough, la purge de la corbeille, le GC et la rétention planifiés supprimeraient
+    // sous le compte partagé, sans trace de l'appelant dans le journal de Zot : ils ne tournent
+    // que si cette option l'accepte explicitement. Les déclenchements manuels (routes admin)
+    // utilisent toujours les identifiants de l'appelant
+    pub background_deletions: bool,
+}
+
+impl Default for ZotConfig {
+    fn default() -> Self {
+        ZotConfig {
+            url: "http://localhost:5000".to_string(),
+            username: "user".to_string(),
+            password: "password".to_string(),
+            passthrough: false,
+            background_deletions: false,
+        }
+    }
+}
+
+impl ZotConfig {
+    pub fn service_credentials(&self) -> RegistryCredentials {
+        RegistryCredentials::Basic {
+            username: self.username.clone(),
+            password: self.password.clone(),
+        }
+    }
+}
+
+// Identifiants présentés à Zot pour une requête
+#[derive(Clone)]
+pub enum RegistryCredentials {
+    Basic { username: String, password: String },
+    Bearer(String),
+}
+
+impl RegistryCredentials {
+    pub fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        match self {
+            RegistryCredentials::Basic { username, password } => {
+                request.basic_auth(username, Some(password))
+            }
+            RegistryCredentials::Bearer(token) => request.bearer_auth(token),
+        }
+    }
 }
 
 pub struct AppState {
     pub zot_config: ZotConfig,
-    pub client: Mutex<Client>,
+    // Appels à Zot : CA, identité mTLS et options de vérification propres au registre
+    pub registry_client: Mutex<Client>,
+    // Appels vers les autres hôtes (JWKS, webhooks, téléchargements)
+    pub http_client: Client,
+    pub trash_config: TrashConfig,
+    pub gc_config: GcConfig,
+    pub history_config: HistoryConfig,
+    pub blob_ledger: BlobLedger,
+    pub retention_config: RetentionConfig,
+    pub auth_config: AuthConfig,
+    pub jwks_cache: JwksCache,
+    pub audit_log: AuditLog,
+    pub webhook_config: WebhookConfig,
+    pub webhook_queue: WebhookQueue,
+    pub events_config: EventsConfig,
+    pub event_bus: EventBus,
+    pub health_config: HealthConfig,
+    pub health_cache: HealthCache,
+    pub upload_config: UploadConfig,
+    pub idempotency_store: IdempotencyStore,
+    pub jobs_config: JobsConfig,
+    pub job_store: JobStore,
 }
\ No newline at end of file
@@ -0,0 +1,60 @@
+use chrono::Utc;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use tokio::sync::watch;
+
+use crate::webhooks::WebhookPayload;
+
+#[derive(Clone)]
+pub struct StreamEvent {
+    pub id: u64,
+    pub payload: WebhookPayload,
+}
+
+// Tampon circulaire des derniers événements, rejoué aux clients SSE qui reprennent un flux
+pub struct EventBus {
+    capacity: usize,
+    events: Mutex<VecDeque<StreamEvent>>,
+    // Identifiant du dernier événement publié, pour réveiller les abonnés
+    latest: watch::Sender<u64>,
+}
+
+impl EventBus {
+    pub fn new(capacity: usize) -> Self {
+        // Identifiants initialisés sur l'horloge : un Last-Event-ID d'une exécution précédente
+        // reste inférieur aux nouveaux
+        let (latest, _) = watch::channel(Utc::now().timestamp_micros().max(0) as u64);
+        EventBus {
+            capacity: capacity.max(1),
+            events: Mutex::new(VecDeque::new()),
+            latest,
+        }
+    }
+
+    pub fn publish(&self, payload: WebhookPayload) {
+        let mut events = self.events.lock().unwrap();
+        let id = *self.latest.borrow() + 1;
+        events.push_back(StreamEvent { id, payload });
+        while events.len() > self.capacity {
+            events.pop_front();
+        }
+        self.latest.send_replace(id);
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<u64> {
+        self.latest.subscribe()
+    }
+
+    pub fn latest_id(&self) -> u64 {
+        *self.latest.borrow()
+    }
+
+    pub fn since(&self, last_id: u64) -> Vec<StreamEvent> {
+        let events = self.events.lock().unwrap();
+        events
+            .iter()
+            .filter(|event| event.id > last_id)
+            .cloned()
+            .collect()
+    }
+}
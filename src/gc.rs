@@ -0,0 +1,241 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::deletion::{reachable_from, tagged_manifests};
+use crate::entities::{AppState, RegistryCredentials};
+use crate::resilience::ZotError;
+use crate::services::delete_blob;
+use crate::store::JsonFile;
+
+// L'API OCI ne permet pas de lister les blobs d'un dépôt : le GC ne considère donc que les blobs
+// poussés par ce service, consignés dans un registre local persistant
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LedgerEntry {
+    pub repository: String,
+    pub digest: String,
+    pub size: u64,
+    pub uploaded_at: DateTime<Utc>,
+    // Date à laquelle le GC a constaté que le blob n'était plus référencé
+    pub unreferenced_since: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CollectedBlob {
+    pub digest: String,
+    pub size: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct RepositoryReport {
+    pub repository: String,
+    pub reachable: usize,
+    pub deleted: Vec<CollectedBlob>,
+    // Blobs non référencés encore protégés par le délai de grâce
+    pub pending: Vec<CollectedBlob>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct GcReport {
+    pub dry_run: bool,
+    pub started_at: DateTime<Utc>,
+    pub repositories: Vec<RepositoryReport>,
+    pub bytes_reclaimed: u64,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct LedgerData {
+    blobs: Vec<LedgerEntry>,
+    bytes_reclaimed: u64,
+    last_report: Option<GcReport>,
+}
+
+#[derive(Serialize)]
+pub struct GcStatus {
+    pub tracked_blobs: usize,
+    pub bytes_reclaimed: u64,
+    pub last_report: Option<GcReport>,
+}
+
+pub struct BlobLedger {
+    file: JsonFile,
+    data: Mutex<LedgerData>,
+}
+
+impl BlobLedger {
+    // Un registre illisible empêche le démarrage : l'ignorer rendrait ses blobs irrécupérables
+    pub fn open(path: PathBuf) -> Result<Self, String> {
+        let file = JsonFile::new(path);
+        let data = file.load()?;
+        Ok(BlobLedger {
+            file,
+            data: Mutex::new(data),
+        })
+    }
+
+    fn save(&self, data: &LedgerData) {
+        if let Err(e) = self.file.save(data) {
+            tracing::error!(path = %self.file.path().display(), error = %e, "Erreur écriture registre GC");
+        }
+    }
+
+    pub fn record(&self, repository: &str, digest: &str, size: u64) {
+        let mut data = self.data.lock().unwrap();
+        match data
+            .blobs
+            .iter_mut()
+            .find(|entry| entry.repository == repository && entry.digest == digest)
+        {
+            Some(entry) => {
+                entry.uploaded_at = Utc::now();
+                entry.unreferenced_since = None;
+            }
+            None => data.blobs.push(LedgerEntry {
+                repository: repository.to_string(),
+                digest: digest.to_string(),
+                size,
+                uploaded_at: Utc::now(),
+                unreferenced_since: None,
+            }),
+        }
+        self.save(&data);
+    }
+
+    fn entries(&self, repository: &str) -> Vec<LedgerEntry> {
+        let data = self.data.lock().unwrap();
+        data.blobs
+            .iter()
+            .filter(|entry| entry.repository == repository)
+            .cloned()
+            .collect()
+    }
+
+    fn repositories(&self) -> Vec<String> {
+        let data = self.data.lock().unwrap();
+        let mut repositories: Vec<String> = data
+            .blobs
+            .iter()
+            .map(|entry| entry.repository.clone())
+            .collect();
+        repositories.sort();
+        repositories.dedup();
+        repositories
+    }
+
+    fn update(&self, repository: &str, digest: &str, unreferenced_since: Option<DateTime<Utc>>) {
+        let mut data = self.data.lock().unwrap();
+        if let Some(entry) = data
+            .blobs
+            .iter_mut()
+            .find(|entry| entry.repository == repository && entry.digest == digest)
+        {
+            entry.unreferenced_since = unreferenced_since;
+        }
+        self.save(&data);
+    }
+
+    fn remove(&self, repository: &str, digest: &str) {
+        let mut data = self.data.lock().unwrap();
+        data.blobs
+            .retain(|entry| !(entry.repository == repository && entry.digest == digest));
+        self.save(&data);
+    }
+
+    fn finish_run(&self, report: &GcReport) {
+        let mut data = self.data.lock().unwrap();
+        data.bytes_reclaimed += report.bytes_reclaimed;
+        data.last_report = Some(report.clone());
+        self.save(&data);
+    }
+
+    pub fn status(&self) -> GcStatus {
+        let data = self.data.lock().unwrap();
+        GcStatus {
+            tracked_blobs: data.blobs.len(),
+            bytes_reclaimed: data.bytes_reclaimed,
+            last_report: data.last_report.clone(),
+        }
+    }
+}
+
+pub async fn collect_garbage(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: Option<&str>,
+    dry_run: bool,
+) -> Result<GcReport, ZotError> {
+    let ledger = &state.blob_ledger;
+    let grace_period = Duration::minutes(state.gc_config.grace_period_minutes);
+    let now = Utc::now();
+
+    let repositories = match repository {
+        Some(repository) => vec![repository.to_string()],
+        None => ledger.repositories(),
+    };
+
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+    let mut report = GcReport {
+        dry_run,
+        started_at: now,
+        repositories: Vec::new(),
+        bytes_reclaimed: 0,
+    };
+
+    for repository in repositories {
+        // Les index `_history.*` sont des tags : les versions qu'ils listent restent vivantes
+        // (voir `history.max_entries`)
+        let tagged = tagged_manifests(state, credentials, &repository).await?;
+        let roots: Vec<&[u8]> = tagged.iter().map(|m| m.raw.as_slice()).collect();
+        let reachable = reachable_from(state, credentials, &repository, &roots).await?;
+
+        let mut repository_report = RepositoryReport {
+            repository: repository.clone(),
+            reachable: reachable.blobs.len(),
+            ..Default::default()
+        };
+
+        for entry in ledger.entries(&repository) {
+            if reachable.blobs.contains(&entry.digest) {
+                if entry.unreferenced_since.is_some() && !dry_run {
+                    ledger.update(&repository, &entry.digest, None);
+                }
+                continue;
+            }
+
+            let since = entry.unreferenced_since.unwrap_or(now);
+            if entry.unreferenced_since.is_none() && !dry_run {
+                ledger.update(&repository, &entry.digest, Some(now));
+            }
+
+            let blob = CollectedBlob {
+                digest: entry.digest.clone(),
+                size: entry.size,
+            };
+            if now - since < grace_period {
+                repository_report.pending.push(blob);
+                continue;
+            }
+
+            if !dry_run {
+                let deleted =
+                    delete_blob(&client, &zot.url, &repository, &entry.digest, credentials).await?;
+                ledger.remove(&repository, &entry.digest);
+                // Déjà supprimé par ailleurs : rien de récupéré
+                if !deleted {
+                    continue;
+                }
+            }
+            report.bytes_reclaimed += blob.size;
+            repository_report.deleted.push(blob);
+        }
+
+        report.repositories.push(repository_report);
+    }
+
+    if !dry_run {
+        ledger.finish_run(&report);
+    }
+    Ok(report)
+}
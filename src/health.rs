@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::entities::AppState;
+use crate::services::ping_registry;
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistryStatus {
+    Up,
+    // Zot répond mais refuse les identifiants du service
+    Unauthorized,
+    Unreachable,
+    Error,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RegistryHealth {
+    pub name: String,
+    pub url: String,
+    pub status: RegistryStatus,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct Readiness {
+    pub ready: bool,
+    pub registries: Vec<RegistryHealth>,
+}
+
+// Dernier résultat des sondes, réutilisé pendant `cache_seconds`
+#[derive(Default)]
+pub struct HealthCache {
+    last: Mutex<Option<(Instant, Readiness)>>,
+}
+
+async fn probe_zot(state: &AppState) -> RegistryHealth {
+    let client = state.registry_client.lock().unwrap().clone();
+    let timeout = Duration::from_secs(state.health_config.timeout_seconds.max(1));
+    let started = Instant::now();
+    let result = ping_registry(
+        &client,
+        &state.zot_config.url,
+        &state.zot_config.service_credentials(),
+        timeout,
+    )
+    .await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let (status, error) = match result {
+        Ok(code) if code.is_success() => (RegistryStatus::Up, None),
+        Ok(code) if code == StatusCode::UNAUTHORIZED || code == StatusCode::FORBIDDEN => (
+            RegistryStatus::Unauthorized,
+            Some(format!("Identifiants refusés: {}", code)),
+        ),
+        Ok(code) => (
+            RegistryStatus::Error,
+            Some(format!("Statut inattendu: {}", code)),
+        ),
+        Err(e) if e.is_timeout() => (
+            RegistryStatus::Unreachable,
+            Some(format!("Pas de réponse en {} s", timeout.as_secs())),
+        ),
+        Err(e) => (RegistryStatus::Unreachable, Some(e.to_string())),
+    };
+
+    RegistryHealth {
+        name: "zot".to_string(),
+        url: state.zot_config.url.clone(),
+        status,
+        latency_ms,
+        error,
+        checked_at: Utc::now(),
+    }
+}
+
+pub async fn check_readiness(state: &AppState) -> Readiness {
+    let max_age = Duration::from_secs(state.health_config.cache_seconds);
+    if let Some((checked, readiness)) = state.health_cache.last.lock().unwrap().as_ref() {
+        if checked.elapsed() < max_age {
+            return readiness.clone();
+        }
+    }
+
+    let registries = vec![probe_zot(state).await];
+    let readiness = Readiness {
+        ready: registries
+            .iter()
+            .all(|registry| registry.status == RegistryStatus::Up),
+        registries,
+    };
+    *state.health_cache.last.lock().unwrap() = Some((Instant::now(), readiness.clone()));
+    readiness
+}
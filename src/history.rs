@@ -0,0 +1,205 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::deletion::validate_reference;
+use crate::entities::{AppState, Descriptor, ImageIndex, Operation, RegistryCredentials};
+use crate::manifest_builder::{manifest_descriptor, INDEX_MEDIA_TYPE, MANIFEST_MEDIA_TYPE};
+use crate::resilience::ZotError;
+use crate::services::{fetch_manifest, fetch_manifest_raw, manifest_url, put_manifest};
+
+// L'historique d'un tag est un index OCI tagué `_history.{reference}` : il liste les manifests
+// successivement pointés par le tag, ce qui les garde référencés pour un éventuel rollback
+pub const HISTORY_TAG_PREFIX: &str = "_history.";
+const RECORDED_ANNOTATION: &str = "com.aneocorp.history.recorded";
+const OPERATION_ANNOTATION: &str = "com.aneocorp.history.operation";
+
+pub fn history_tag(reference: &str) -> String {
+    format!("{}{}", HISTORY_TAG_PREFIX, reference)
+}
+
+#[derive(Serialize)]
+pub struct HistoryEntry {
+    pub digest: String,
+    pub size: i64,
+    pub recorded_at: Option<String>,
+    pub operation: Option<String>,
+    pub current: bool,
+}
+
+pub async fn load_history(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    reference: &str,
+) -> Result<Vec<Descriptor>, ZotError> {
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+
+    let index = fetch_manifest::<ImageIndex>(
+        &client,
+        &zot.url,
+        repository,
+        &history_tag(reference),
+        credentials,
+    )
+    .await?;
+    Ok(index.map(|(index, _)| index.manifests).unwrap_or_default())
+}
+
+// Fait pointer le tag sur le manifest fourni puis ajoute ce digest à l'historique du tag
+pub async fn tag_manifest(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    reference: &str,
+    media_type: &str,
+    body: Vec<u8>,
+    operation: Operation,
+) -> Result<Descriptor, ZotError> {
+    // Vérifié avant de déplacer le tag : l'historique doit pouvoir être écrit ensuite
+    validate_reference(reference)?;
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+
+    let previous = fetch_manifest_raw(&client, &zot.url, repository, reference, credentials)
+        .await?
+        .map(|raw| descriptor_of(&raw));
+
+    let descriptor = manifest_descriptor(media_type, &body);
+    put_manifest(
+        &client,
+        &manifest_url(&zot.url, repository, reference),
+        credentials,
+        media_type,
+        body,
+    )
+    .await?;
+
+    record_history(
+        state,
+        credentials,
+        repository,
+        reference,
+        previous,
+        &descriptor,
+        operation,
+    )
+    .await
+    .map_err(|e| e.context("Manifest tagué mais historique non enregistré"))?;
+
+    Ok(descriptor)
+}
+
+async fn record_history(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    reference: &str,
+    previous: Option<Descriptor>,
+    current: &Descriptor,
+    operation: Operation,
+) -> Result<(), ZotError> {
+    let mut entries = load_history(state, credentials, repository, reference).await?;
+
+    // Tag créé avant l'historique ou modifié hors de l'API : on conserve la version remplacée
+    if let Some(previous) = previous {
+        if entries.last().map(|last| &last.digest) != Some(&previous.digest) {
+            entries.push(previous);
+        }
+    }
+
+    if entries.last().map(|last| &last.digest) == Some(&current.digest) {
+        return Ok(());
+    }
+
+    let mut entry = current.clone();
+    let mut annotations = serde_json::Map::new();
+    annotations.insert(
+        RECORDED_ANNOTATION.to_string(),
+        Value::String(Utc::now().to_rfc3339()),
+    );
+    annotations.insert(
+        OPERATION_ANNOTATION.to_string(),
+        Value::String(operation.as_str().to_string()),
+    );
+    entry.annotations = Some(annotations);
+    entries.push(entry);
+
+    let max_entries = state.history_config.max_entries.max(1);
+    if entries.len() > max_entries {
+        entries.drain(..entries.len() - max_entries);
+    }
+
+    let index = ImageIndex {
+        schema_version: 2,
+        media_type: INDEX_MEDIA_TYPE.to_string(),
+        manifests: entries,
+        annotations: None,
+    };
+    let body = serde_json::to_vec(&index).map_err(|e| format!("Erreur sérialisation: {}", e))?;
+
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+    put_manifest(
+        &client,
+        &manifest_url(&zot.url, repository, &history_tag(reference)),
+        credentials,
+        INDEX_MEDIA_TYPE,
+        body,
+    )
+    .await
+}
+
+// Date à laquelle le tag a été posé sur `digest` pour la dernière fois, d'après l'index
+// d'historique brut du tag
+pub fn recorded_at(index: &[u8], digest: &str) -> Option<DateTime<Utc>> {
+    let index: ImageIndex = serde_json::from_slice(index).ok()?;
+    let entry = index
+        .manifests
+        .iter()
+        .rev()
+        .find(|entry| entry.digest == digest)?;
+    let recorded = entry
+        .annotations
+        .as_ref()?
+        .get(RECORDED_ANNOTATION)?
+        .as_str()?;
+    Some(
+        DateTime::parse_from_rfc3339(recorded)
+            .ok()?
+            .with_timezone(&Utc),
+    )
+}
+
+pub fn history_entries(descriptors: Vec<Descriptor>, current: Option<&str>) -> Vec<HistoryEntry> {
+    descriptors
+        .into_iter()
+        .map(|descriptor| {
+            let annotation = |key: &str| {
+                descriptor
+                    .annotations
+                    .as_ref()
+                    .and_then(|annotations| annotations.get(key))
+                    .and_then(|value| value.as_str())
+                    .map(|value| value.to_string())
+            };
+            HistoryEntry {
+                recorded_at: annotation(RECORDED_ANNOTATION),
+                operation: annotation(OPERATION_ANNOTATION),
+                current: Some(descriptor.digest.as_str()) == current,
+                digest: descriptor.digest,
+                size: descriptor.size,
+            }
+        })
+        .collect()
+}
+
+// Le type média d'un manifest déjà stocké est lu dans son propre corps
+pub fn descriptor_of(raw: &[u8]) -> Descriptor {
+    let media_type = serde_json::from_slice::<Value>(raw)
+        .ok()
+        .and_then(|value| value.get("mediaType")?.as_str().map(|s| s.to_string()))
+        .unwrap_or_else(|| MANIFEST_MEDIA_TYPE.to_string());
+    manifest_descriptor(&media_type, raw)
+}
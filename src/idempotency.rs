@@ -0,0 +1,240 @@
+use actix_web::body::{to_bytes, BoxBody};
+use actix_web::http::header::{CONTENT_TYPE, LOCATION};
+use actix_web::{HttpRequest, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::auth::Caller;
+use crate::services::calculate_sha256;
+use crate::store::JsonFile;
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+// Présent sur une réponse rejouée depuis le stockage
+pub const REPLAYED_HEADER: &str = "Idempotent-Replayed";
+const MAX_KEY_LENGTH: usize = 255;
+
+#[derive(Serialize, Deserialize, Clone)]
+struct StoredResponse {
+    status: u16,
+    content_type: Option<String>,
+    // Adresse de la tâche créée par une publication asynchrone (202)
+    #[serde(default)]
+    location: Option<String>,
+    body: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IdempotencyRecord {
+    caller: String,
+    key: String,
+    // Méthode, chemin et contenu de la requête ; une clé réutilisée doit décrire la même requête
+    request_hash: String,
+    created_at: DateTime<Utc>,
+    // Absente tant que la première requête est en cours
+    response: Option<StoredResponse>,
+}
+
+enum Claim {
+    Execute,
+    Replay(StoredResponse),
+    InProgress,
+    Mismatch,
+}
+
+// Clés reçues et réponses associées, persistées pour survivre aux redémarrages ; une clé est
+// propre à chaque appelant
+pub struct IdempotencyStore {
+    file: JsonFile,
+    records: Mutex<Vec<IdempotencyRecord>>,
+    // Durée pendant laquelle une clé rejoue la réponse d'origine
+    window: Duration,
+}
+
+impl IdempotencyStore {
+    pub fn open(path: PathBuf, window_hours: u64) -> Result<Self, String> {
+        let file = JsonFile::new(path);
+        let mut records: Vec<IdempotencyRecord> = file.load()?;
+        // Une requête interrompue par l'arrêt du service pourra être rejouée
+        records.retain(|record| record.response.is_some());
+        Ok(IdempotencyStore {
+            file,
+            records: Mutex::new(records),
+            window: Duration::hours(window_hours.max(1) as i64),
+        })
+    }
+
+    fn save(&self, records: &[IdempotencyRecord]) {
+        if let Err(e) = self.file.save(records) {
+            tracing::error!(path = %self.file.path().display(), error = %e, "Erreur écriture clés d'idempotence");
+        }
+    }
+
+    fn claim(&self, caller: &str, key: &str, request_hash: &str) -> Claim {
+        let now = Utc::now();
+        let mut records = self.records.lock().unwrap();
+        records.retain(|record| record.created_at + self.window > now);
+
+        let claim = match records
+            .iter()
+            .find(|record| record.caller == caller && record.key == key)
+        {
+            Some(record) if record.request_hash != request_hash => Claim::Mismatch,
+            Some(record) => match &record.response {
+                Some(response) => Claim::Replay(response.clone()),
+                None => Claim::InProgress,
+            },
+            None => {
+                records.push(IdempotencyRecord {
+                    caller: caller.to_string(),
+                    key: key.to_string(),
+                    request_hash: request_hash.to_string(),
+                    created_at: now,
+                    response: None,
+                });
+                Claim::Execute
+            }
+        };
+        self.save(&records);
+        claim
+    }
+
+    // Sans réponse à conserver (erreur serveur), la clé est libérée pour une nouvelle tentative
+    fn complete(&self, caller: &str, key: &str, response: Option<StoredResponse>) {
+        let mut records = self.records.lock().unwrap();
+        match response {
+            Some(response) => {
+                if let Some(record) = records
+                    .iter_mut()
+                    .find(|record| record.caller == caller && record.key == key)
+                {
+                    record.response = Some(response);
+                }
+            }
+            None => records.retain(|record| !(record.caller == caller && record.key == key)),
+        }
+        self.save(&records);
+    }
+}
+
+// Clé réservée par une requête en cours. Si l'opération n'aboutit pas (client déconnecté, futur
+// abandonné, panique), la clé est libérée à la destruction du garde : sans cela, toute
+// nouvelle tentative recevrait 409 jusqu'à l'expiration de la fenêtre
+struct PendingClaim<'a> {
+    store: &'a IdempotencyStore,
+    caller: &'a str,
+    key: &'a str,
+    completed: bool,
+}
+
+impl PendingClaim<'_> {
+    fn complete(mut self, response: Option<StoredResponse>) {
+        self.completed = true;
+        self.store.complete(self.caller, self.key, response);
+    }
+}
+
+impl Drop for PendingClaim<'_> {
+    fn drop(&mut self) {
+        if !self.completed {
+            tracing::warn!(
+                key = self.key,
+                "Requête idempotente interrompue: clé libérée"
+            );
+            self.store.complete(self.caller, self.key, None);
+        }
+    }
+}
+
+fn replay(stored: StoredResponse) -> HttpResponse {
+    let status = actix_web::http::StatusCode::from_u16(stored.status)
+        .unwrap_or(actix_web::http::StatusCode::OK);
+    let mut response = HttpResponse::build(status);
+    response.insert_header((REPLAYED_HEADER, "true"));
+    if let Some(content_type) = stored.content_type {
+        response.content_type(content_type);
+    }
+    if let Some(location) = stored.location {
+        response.insert_header((LOCATION, location));
+    }
+    response.body(stored.body)
+}
+
+// Exécute l'opération une seule fois par clé d'idempotence : une requête identique reçue
+// pendant la fenêtre configurée reçoit la réponse d'origine. `fingerprint` résume le contenu
+// de la requête, indépendamment de son encodage
+pub async fn idempotent(
+    store: &IdempotencyStore,
+    req: &HttpRequest,
+    caller: &Caller,
+    fingerprint: &str,
+    operation: impl Future<Output = HttpResponse>,
+) -> HttpResponse {
+    let key = match req.headers().get(IDEMPOTENCY_KEY_HEADER) {
+        Some(value) => match value.to_str() {
+            Ok(key) if !key.trim().is_empty() && key.len() <= MAX_KEY_LENGTH => key.trim(),
+            _ => {
+                return HttpResponse::BadRequest()
+                    .body(format!("{} invalide", IDEMPOTENCY_KEY_HEADER))
+            }
+        },
+        None => return operation.await,
+    };
+
+    let request_hash = calculate_sha256(
+        format!(
+            "{} {}?{}\n{}",
+            req.method(),
+            req.path(),
+            req.query_string(),
+            fingerprint
+        )
+        .as_bytes(),
+    );
+    match store.claim(&caller.name, key, &request_hash) {
+        Claim::Replay(stored) => replay(stored),
+        Claim::InProgress => HttpResponse::Conflict()
+            .body("Une requête avec cette Idempotency-Key est déjà en cours"),
+        Claim::Mismatch => HttpResponse::UnprocessableEntity()
+            .body("Idempotency-Key déjà utilisée pour une requête différente"),
+        Claim::Execute => {
+            let pending = PendingClaim {
+                store,
+                caller: &caller.name,
+                key,
+                completed: false,
+            };
+            let response = operation.await;
+            let status = response.status();
+            let header = |name| {
+                response
+                    .headers()
+                    .get(name)
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.to_string())
+            };
+            let content_type = header(CONTENT_TYPE);
+            let location = header(LOCATION);
+
+            let (response, body) = response.into_parts();
+            let body = match to_bytes(body).await {
+                Ok(body) => body,
+                Err(e) => {
+                    pending.complete(None);
+                    return HttpResponse::InternalServerError()
+                        .body(format!("Erreur lecture réponse: {}", e));
+                }
+            };
+            let stored = (!status.is_server_error()).then(|| StoredResponse {
+                status: status.as_u16(),
+                content_type,
+                location,
+                body: String::from_utf8_lossy(&body).into_owned(),
+            });
+            pending.complete(stored);
+            response.set_body(BoxBody::new(body))
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use actix_web::http::header::LOCATION;
+use actix_web::{rt, web, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+
+use crate::audit::{audited, AuditAction};
+use crate::auth::Caller;
+use crate::entities::{AppState, ManifestMetadata, Operation};
+use crate::publish::{plan_component, push_plan, ConfigSource, LayerSource, PlannedBlob};
+use crate::resilience::error_response;
+use crate::store::JsonFile;
+use crate::upload::{Attachment, ComponentUpload, SpooledWasm};
+
+// Délai suggéré quand la file des publications est pleine
+const QUEUE_RETRY_AFTER_SECONDS: u64 = 30;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BlobProgress {
+    pub media_type: String,
+    pub digest: String,
+    pub size: u64,
+    // Octets transmis à Zot ; repart de zéro si l'envoi du blob est repris
+    pub sent: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Job {
+    pub id: String,
+    pub caller: String,
+    pub repository: String,
+    pub reference: String,
+    pub state: JobState,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    // Connus une fois le manifest planifié
+    pub blobs: Vec<BlobProgress>,
+    pub digest: Option<String>,
+    pub error: Option<String>,
+}
+
+// Publications asynchrones, persistées pour que leur issue reste consultable après un
+// redémarrage ; une tâche interrompue ne reprend pas, le fichier téléversé étant perdu
+pub struct JobStore {
+    file: JsonFile,
+    jobs: Mutex<Vec<Job>>,
+    sequence: AtomicU64,
+    // Publications exécutées simultanément ; les suivantes restent en file
+    slots: Semaphore,
+}
+
+// Publication en attente : le binaire, déjà décrit par sa couche, n'est plus gardé en mémoire
+// et sera relu depuis son fichier temporaire, supprimé avec la tâche
+struct QueuedPush {
+    metadata: ManifestMetadata,
+    wasm: SpooledWasm,
+    attachments: Vec<Attachment>,
+}
+
+impl JobStore {
+    pub fn open(path: PathBuf, max_concurrent: usize) -> Result<Self, String> {
+        let file = JsonFile::new(path);
+        let mut jobs: Vec<Job> = file.load()?;
+        for job in jobs.iter_mut() {
+            if matches!(job.state, JobState::Queued | JobState::Running) {
+                job.state = JobState::Failed;
+                job.error = Some("Interrompue par l'arrêt du service".to_string());
+                job.updated_at = Utc::now();
+            }
+        }
+        Ok(JobStore {
+            file,
+            jobs: Mutex::new(jobs),
+            sequence: AtomicU64::new(0),
+            slots: Semaphore::new(max_concurrent.max(1)),
+        })
+    }
+
+    fn save(&self, jobs: &[Job]) {
+        if let Err(e) = self.file.save(jobs) {
+            tracing::error!(path = %self.file.path().display(), error = %e, "Erreur écriture tâches");
+        }
+    }
+
+    // Les tâches terminées depuis plus de `retention` sont oubliées ; None si `max_queued`
+    // tâches attendent déjà
+    fn create(
+        &self,
+        caller: &str,
+        repository: &str,
+        reference: &str,
+        retention: Duration,
+        max_queued: usize,
+    ) -> Option<Job> {
+        let now = Utc::now();
+        let job = Job {
+            id: format!(
+                "{}-{}",
+                now.timestamp_micros(),
+                self.sequence.fetch_add(1, Ordering::Relaxed)
+            ),
+            caller: caller.to_string(),
+            repository: repository.to_string(),
+            reference: reference.to_string(),
+            state: JobState::Queued,
+            created_at: now,
+            updated_at: now,
+            blobs: Vec::new(),
+            digest: None,
+            error: None,
+        };
+
+        let mut jobs = self.jobs.lock().unwrap();
+        let queued = jobs
+            .iter()
+            .filter(|job| job.state == JobState::Queued)
+            .count();
+        if queued >= max_queued {
+            return None;
+        }
+        jobs.retain(|job| {
+            matches!(job.state, JobState::Queued | JobState::Running)
+                || job.updated_at + retention > now
+        });
+        jobs.push(job.clone());
+        self.save(&jobs);
+        Some(job)
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        let jobs = self.jobs.lock().unwrap();
+        jobs.iter().find(|job| job.id == id).cloned()
+    }
+
+    fn update(&self, id: &str, change: impl FnOnce(&mut Job)) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.iter_mut().find(|job| job.id == id) {
+            change(job);
+            job.updated_at = Utc::now();
+        }
+        self.save(&jobs);
+    }
+
+    fn start(&self, id: &str, blobs: &[PlannedBlob]) {
+        self.update(id, |job| {
+            job.state = JobState::Running;
+            job.blobs = blobs
+                .iter()
+                .map(|blob| BlobProgress {
+                    media_type: blob.media_type.clone(),
+                    digest: blob.digest.clone(),
+                    size: blob.size as u64,
+                    sent: 0,
+                })
+                .collect();
+        });
+    }
+
+    // Non persistée : une tâche interrompue échoue au redémarrage, sa progression n'a plus cours
+    fn blob_progress(&self, id: &str, digest: &str, sent: u64) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(job) = jobs.iter_mut().find(|job| job.id == id) {
+            for blob in job.blobs.iter_mut().filter(|blob| blob.digest == digest) {
+                blob.sent = sent;
+            }
+        }
+    }
+
+    fn finish(&self, id: &str, result: Result<String, String>) {
+        self.update(id, |job| match result {
+            Ok(digest) => {
+                job.state = JobState::Succeeded;
+                job.digest = Some(digest);
+            }
+            Err(e) => {
+                job.state = JobState::Failed;
+                job.error = Some(e);
+            }
+        });
+    }
+}
+
+// Enregistre la publication et rend la main aussitôt (202) ; le binaire reste dans son fichier
+// temporaire jusqu'à la fin de la tâche. Le manifest et le binaire sont déjà validés
+pub fn submit_push(
+    state: &web::Data<AppState>,
+    caller: &Caller,
+    upload: ComponentUpload,
+    created: DateTime<Utc>,
+) -> HttpResponse {
+    let push = match upload {
+        ComponentUpload {
+            manifest: Some(metadata),
+            wasm: Some(wasm),
+            attachments,
+        } => QueuedPush {
+            metadata,
+            wasm,
+            attachments,
+        },
+        _ => return HttpResponse::BadRequest().body("Manifest.json ou fichier .wasm manquant"),
+    };
+    let repository = push.metadata.metadata.name.clone();
+    let reference = push.metadata.metadata.annotations.version.clone();
+    let retention = Duration::hours(state.jobs_config.retention_hours.max(1) as i64);
+    let job = match state.job_store.create(
+        &caller.name,
+        &repository,
+        &reference,
+        retention,
+        state.jobs_config.max_queued.max(1),
+    ) {
+        Some(job) => job,
+        None => {
+            return HttpResponse::ServiceUnavailable()
+                .insert_header(("Retry-After", QUEUE_RETRY_AFTER_SECONDS.to_string()))
+                .body("File des publications pleine")
+        }
+    };
+
+    let id = job.id.clone();
+    let state = state.clone();
+    let caller = caller.clone();
+    rt::spawn(async move {
+        run_push(state, &caller, &id, &repository, &reference, push, created).await;
+    });
+
+    HttpResponse::Accepted()
+        .insert_header((LOCATION, format!("/api/v1/jobs/{}", job.id)))
+        .json(job)
+}
+
+async fn run_push(
+    state: web::Data<AppState>,
+    caller: &Caller,
+    id: &str,
+    repository: &str,
+    reference: &str,
+    push: QueuedPush,
+    created: DateTime<Utc>,
+) {
+    let store = &state.job_store;
+    let _slot = store.slots.acquire().await;
+
+    let plan = match plan_component(
+        &push.metadata,
+        ConfigSource::Upload,
+        LayerSource::Spooled(&push.wasm, &push.attachments),
+        created,
+    ) {
+        Ok(plan) => plan,
+        Err(e) => return store.finish(id, Err(e)),
+    };
+    store.start(id, &plan.blobs);
+
+    let mut result = None;
+    audited(
+        &state,
+        caller,
+        AuditAction::Push,
+        repository,
+        Some(reference),
+        async {
+            let outcome = push_plan(
+                &state,
+                &caller.credentials,
+                repository,
+                reference,
+                plan,
+                Operation::Push,
+                |blob| {
+                    let state = state.clone();
+                    let id = id.to_string();
+                    let digest = blob.digest.clone();
+                    Arc::new(move |sent| state.job_store.blob_progress(&id, &digest, sent))
+                },
+            )
+            .await;
+            let response = match &outcome {
+                Ok(_) => HttpResponse::Ok().body("Upload réussi!"),
+                Err(e) => error_response(e.clone()),
+            };
+            result = Some(
+                outcome
+                    .map(|descriptor| descriptor.digest)
+                    .map_err(|e| e.message),
+            );
+            response
+        },
+    )
+    .await;
+
+    if let Some(result) = result {
+        store.finish(id, result);
+    }
+}
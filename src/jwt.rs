@@ -0,0 +1,185 @@
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde_json::Value;
+use std::fs;
+use std::str::FromStr;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use crate::auth::{Caller, Grant};
+use crate::config::JwtConfig;
+use crate::entities::{AppState, RegistryCredentials};
+
+// Délai minimal entre deux rechargements forcés (clé inconnue), pour ne pas marteler l'IdP
+const MIN_FORCED_REFRESH: Duration = Duration::from_secs(60);
+
+struct CachedKeys {
+    keys: JwkSet,
+    fetched_at: Instant,
+    // Dernier rechargement en échec : les clés en cache restent servies en attendant
+    failed_at: Option<Instant>,
+}
+
+#[derive(Default)]
+pub struct JwksCache {
+    keys: RwLock<Option<CachedKeys>>,
+}
+
+impl JwksCache {
+    async fn load(config: &JwtConfig, client: &reqwest::Client) -> Result<JwkSet, String> {
+        if let Some(path) = &config.jwks_file {
+            let content = fs::read(path).map_err(|e| format!("Erreur lecture JWKS: {}", e))?;
+            return serde_json::from_slice(&content)
+                .map_err(|e| format!("Erreur parsing JWKS: {}", e));
+        }
+        let url = config
+            .jwks_url
+            .as_ref()
+            .ok_or("Aucune source JWKS configurée")?;
+        let response = client
+            .get(url)
+            .send()
+            .await
+            .map_err(|e| format!("Erreur récupération JWKS: {}", e))?;
+        if !response.status().is_success() {
+            return Err(format!("Erreur statut JWKS: {}", response.status()));
+        }
+        response
+            .json()
+            .await
+            .map_err(|e| format!("Erreur parsing JWKS: {}", e))
+    }
+
+    async fn keys(
+        &self,
+        config: &JwtConfig,
+        client: &reqwest::Client,
+        force: bool,
+    ) -> Result<JwkSet, String> {
+        let max_age = Duration::from_secs(config.refresh_minutes * 60);
+        if let Some(cached) = self.keys.read().unwrap().as_ref() {
+            let age = cached.fetched_at.elapsed();
+            let fresh = if force {
+                age < MIN_FORCED_REFRESH
+            } else {
+                age < max_age
+            };
+            let failed_recently = cached
+                .failed_at
+                .is_some_and(|failed_at| failed_at.elapsed() < MIN_FORCED_REFRESH);
+            if fresh || failed_recently {
+                return Ok(cached.keys.clone());
+            }
+        }
+
+        // Une panne de l'IdP ne doit pas refuser les jetons signés par des clés déjà connues
+        match Self::load(config, client).await {
+            Ok(keys) => {
+                *self.keys.write().unwrap() = Some(CachedKeys {
+                    keys: keys.clone(),
+                    fetched_at: Instant::now(),
+                    failed_at: None,
+                });
+                Ok(keys)
+            }
+            Err(e) => match self.keys.write().unwrap().as_mut() {
+                Some(cached) => {
+                    tracing::warn!(
+                        error = %e,
+                        age_seconds = cached.fetched_at.elapsed().as_secs(),
+                        "Rechargement du JWKS en échec: clés en cache conservées"
+                    );
+                    cached.failed_at = Some(Instant::now());
+                    Ok(cached.keys.clone())
+                }
+                None => Err(e),
+            },
+        }
+    }
+}
+
+fn claim_strings(claims: &Value, name: &str) -> Vec<String> {
+    match claims.get(name) {
+        Some(Value::String(value)) => vec![value.clone()],
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(|value| value.as_str().map(|s| s.to_string()))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// Valide un JWT émis par l'IdP configuré et traduit ses groupes en droits
+pub async fn authenticate_bearer(
+    state: &AppState,
+    token: &str,
+    credentials: RegistryCredentials,
+) -> Result<Caller, String> {
+    let config = state
+        .auth_config
+        .jwt
+        .as_ref()
+        .ok_or("Authentification JWT non configurée")?;
+
+    let header = decode_header(token).map_err(|e| format!("JWT invalide: {}", e))?;
+    if matches!(
+        header.alg,
+        Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+    ) {
+        return Err("Algorithme JWT non accepté".to_string());
+    }
+    let kid = header.kid.ok_or("JWT sans identifiant de clé (kid)")?;
+
+    let client = state.http_client.clone();
+    let mut jwks = state.jwks_cache.keys(config, &client, false).await?;
+    if jwks.find(&kid).is_none() {
+        // Rotation des clés côté IdP
+        jwks = state.jwks_cache.keys(config, &client, true).await?;
+    }
+    let jwk = jwks.find(&kid).ok_or("Clé de signature inconnue")?;
+    let key = DecodingKey::from_jwk(jwk).map_err(|e| format!("Clé JWKS invalide: {}", e))?;
+
+    // L'algorithme annoncé par le jeton doit figurer dans la configuration et correspondre à
+    // celui de la clé quand le JWKS le précise
+    if !config.algorithms.contains(&header.alg) {
+        return Err("Algorithme JWT non accepté".to_string());
+    }
+    if let Some(key_algorithm) = jwk.common.key_algorithm {
+        if Algorithm::from_str(&key_algorithm.to_string()).ok() != Some(header.alg) {
+            return Err("Algorithme JWT différent de celui de la clé".to_string());
+        }
+    }
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[&config.issuer]);
+    match &config.audience {
+        Some(audience) => validation.set_audience(&[audience]),
+        None => validation.validate_aud = false,
+    }
+
+    let claims = decode::<Value>(token, &key, &validation)
+        .map_err(|e| format!("JWT refusé: {}", e))?
+        .claims;
+
+    let groups = claim_strings(&claims, &config.groups_claim);
+    let grants = config
+        .role_mappings
+        .iter()
+        .filter(|mapping| groups.contains(&mapping.group))
+        .map(|mapping| Grant {
+            role: mapping.role,
+            repositories: mapping.repositories.clone(),
+        })
+        .collect();
+
+    let name = claim_strings(&claims, &config.name_claim)
+        .into_iter()
+        .chain(claim_strings(&claims, "sub"))
+        .next()
+        .ok_or("JWT sans identité (sub)")?;
+
+    Ok(Caller {
+        name,
+        grants,
+        credentials,
+    })
+}
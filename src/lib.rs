@@ -1,4 +1,26 @@
 // src/lib.rs
+pub mod audit;
+pub mod auth;
+pub mod config;
 pub mod entities;
 pub mod controllers;
-pub mod services;
\ No newline at end of file
+pub mod services;
+pub mod manifest_builder;
+pub mod deletion;
+pub mod events;
+pub mod gc;
+pub mod health;
+pub mod history;
+pub mod idempotency;
+pub mod jobs;
+pub mod jwt;
+pub mod metrics;
+pub mod publish;
+pub mod resilience;
+pub mod retention;
+pub mod store;
+pub mod telemetry;
+pub mod tls;
+pub mod trash;
+pub mod upload;
+pub mod webhooks;
\ No newline at end of file
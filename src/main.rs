@@ -1,38 +1,282 @@
-use actix_web::{web, App, HttpServer};
+use actix_web::dev::Service;
+use actix_web::{rt, web, App, HttpServer};
 use poc::{
+    audit::AuditLog,
+    config::{config_path, load_config},
     controllers::{
-        delete_component::delete_component, get_component::get_component,
-        push_component::push_component, update_component::update_component,
+        delete_all_components::delete_all_components, delete_component::delete_component,
+        get_audit_log::get_audit_log, get_component::get_component,
+        get_component_history::get_component_history,
+        get_garbage_collection::get_garbage_collection, get_health::get_health, get_job::get_job,
+        get_metrics::get_metrics, get_readiness::get_readiness,
+        get_retention_report::get_retention_report, list_components::list_components,
+        list_trash::list_trashed_components, list_webhook_deliveries::list_webhook_deliveries,
+        patch_component::patch_component, push_component::push_component,
+        restore_component::restore_component, rollback_component::rollback_component,
+        run_garbage_collection::run_garbage_collection, run_retention::run_retention,
+        stream_events::stream_events, update_component::update_component,
     },
     entities,
+    events::EventBus,
+    gc::{collect_garbage, BlobLedger},
+    health::HealthCache,
+    idempotency::IdempotencyStore,
+    jobs::JobStore,
+    jwt::JwksCache,
+    metrics::track_request,
+    resilience,
+    retention::apply_retention,
+    telemetry::{self, trace_request, REQUEST_ID_HEADER},
+    tls::{self, ReloadingCertificate},
+    trash::purge_expired,
+    webhooks::{deliver_due, WebhookQueue},
 };
-use reqwest::Client;
-use std::sync::Mutex;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info, warn};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    // Configuration de Zot
-    let zot_config = entities::ZotConfig {
-        url: "http://localhost:5000".to_string(),
-        username: "user".to_string(),
-        password: "password".to_string(),
+    // Configuration de Zot et des sous-systèmes
+    let config = load_config().map_err(std::io::Error::other)?;
+    telemetry::init(&config.telemetry);
+    if !Path::new(&config_path()).exists() {
+        warn!(
+            path = config_path(),
+            "Fichier de configuration absent: valeurs par défaut"
+        );
+    }
+    if !config.auth.enabled {
+        warn!("Authentification désactivée (auth.allow_anonymous): l'API est ouverte à tous");
+    }
+    resilience::configure(config.upstream.clone());
+    std::fs::create_dir_all(&config.data_dir)?;
+    let audit_path = config.audit.enabled.then(|| {
+        config
+            .audit
+            .path
+            .clone()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| Path::new(&config.data_dir).join("audit.jsonl"))
+    });
+
+    let registry_client =
+        resilience::build_client(&config.upstream).map_err(std::io::Error::other)?;
+    let http_client = resilience::build_http_client().map_err(std::io::Error::other)?;
+    let server_config = config.server.clone();
+    let certificate = match &server_config.tls {
+        Some(tls) => Some(Arc::new(
+            ReloadingCertificate::load(tls).map_err(std::io::Error::other)?,
+        )),
+        None => None,
     };
 
     let app_state = web::Data::new(entities::AppState {
-        zot_config,
-        client: Mutex::new(Client::new()),
+        zot_config: config.zot,
+        registry_client: Mutex::new(registry_client),
+        http_client,
+        trash_config: config.trash,
+        gc_config: config.gc,
+        history_config: config.history,
+        blob_ledger: BlobLedger::open(Path::new(&config.data_dir).join("gc-ledger.json"))
+            .map_err(std::io::Error::other)?,
+        retention_config: config.retention,
+        auth_config: config.auth,
+        jwks_cache: JwksCache::default(),
+        audit_log: AuditLog::open(audit_path)?,
+        webhook_config: config.webhooks,
+        webhook_queue: WebhookQueue::open(Path::new(&config.data_dir).join("webhooks.json"))
+            .map_err(std::io::Error::other)?,
+        event_bus: EventBus::new(config.events.buffer_size),
+        events_config: config.events,
+        health_config: config.health,
+        health_cache: HealthCache::default(),
+        upload_config: config.uploads,
+        idempotency_store: IdempotencyStore::open(
+            Path::new(&config.data_dir).join("idempotency.json"),
+            config.idempotency.window_hours,
+        )
+        .map_err(std::io::Error::other)?,
+        job_store: JobStore::open(
+            Path::new(&config.data_dir).join("jobs.json"),
+            config.jobs.max_concurrent,
+        )
+        .map_err(std::io::Error::other)?,
+        jobs_config: config.jobs,
+    });
+
+    // Les tâches de fond s'exécutent avec le compte partagé ; en mode passthrough, celles qui
+    // suppriment ne tournent que si `zot.background_deletions` l'accepte
+    let service_credentials = app_state.zot_config.service_credentials();
+    let background_deletions =
+        !app_state.zot_config.passthrough || app_state.zot_config.background_deletions;
+    if !background_deletions {
+        warn!(
+            "Passthrough: purge, GC et rétention planifiés désactivés (zot.background_deletions)"
+        );
+    }
+
+    // Purge périodique de la corbeille
+    if background_deletions {
+        let purge_state = app_state.clone();
+        let purge_credentials = service_credentials.clone();
+        rt::spawn(async move {
+            let period =
+                Duration::from_secs(purge_state.trash_config.purge_interval_minutes.max(1) * 60);
+            let mut interval = rt::time::interval(period);
+            loop {
+                interval.tick().await;
+                match purge_expired(&purge_state, &purge_credentials).await {
+                    Ok(reports) if !reports.is_empty() => {
+                        info!(versions = reports.len(), "Corbeille purgée")
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!(error = %e, "Erreur purge corbeille"),
+                }
+            }
+        });
+    }
+
+    // Garbage collection périodique des blobs orphelins
+    if background_deletions && app_state.gc_config.enabled {
+        let gc_state = app_state.clone();
+        let gc_credentials = service_credentials.clone();
+        rt::spawn(async move {
+            let period = Duration::from_secs(gc_state.gc_config.interval_minutes.max(1) * 60);
+            let mut interval = rt::time::interval(period);
+            loop {
+                interval.tick().await;
+                match collect_garbage(&gc_state, &gc_credentials, None, false).await {
+                    Ok(report) if report.bytes_reclaimed > 0 => {
+                        info!(bytes = report.bytes_reclaimed, "GC: octets récupérés")
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!(error = %e, "Erreur GC"),
+                }
+            }
+        });
+    }
+
+    // Application périodique des politiques de rétention
+    if background_deletions
+        && app_state.retention_config.enabled
+        && !app_state.retention_config.policies.is_empty()
+    {
+        let retention_state = app_state.clone();
+        let retention_credentials = service_credentials.clone();
+        rt::spawn(async move {
+            let period =
+                Duration::from_secs(retention_state.retention_config.interval_minutes.max(1) * 60);
+            let mut interval = rt::time::interval(period);
+            loop {
+                interval.tick().await;
+                match apply_retention(&retention_state, &retention_credentials, None, false).await {
+                    Ok(report) => {
+                        let deleted: usize =
+                            report.repositories.iter().map(|r| r.deleted.len()).sum();
+                        if deleted > 0 {
+                            info!(versions = deleted, "Rétention: versions mises en corbeille")
+                        }
+                    }
+                    Err(e) => error!(error = %e, "Erreur rétention"),
+                }
+            }
+        });
+    }
+
+    // Livraison des webhooks en attente, y compris celles restées en file avant un redémarrage
+    let webhook_state = app_state.clone();
+    rt::spawn(async move {
+        let period = Duration::from_secs(webhook_state.webhook_config.poll_interval_seconds.max(1));
+        let mut interval = rt::time::interval(period);
+        loop {
+            interval.tick().await;
+            deliver_due(&webhook_state).await;
+        }
     });
 
-    println!("Serveur démarré sur http://localhost:8080");
-    HttpServer::new(move || {
+    // Rechargement à chaud du certificat du serveur
+    if let (Some(certificate), Some(tls)) = (&certificate, &server_config.tls) {
+        let certificate = certificate.clone();
+        let period = Duration::from_secs(tls.reload_interval_seconds.max(1));
+        rt::spawn(async move {
+            let mut interval = rt::time::interval(period);
+            loop {
+                interval.tick().await;
+                match certificate.reload_if_changed() {
+                    Ok(true) => info!("Certificat TLS rechargé"),
+                    Ok(false) => {}
+                    Err(e) => error!(error = %e, "Erreur rechargement certificat TLS"),
+                }
+            }
+        });
+    }
+
+    let server = HttpServer::new(move || {
         App::new()
             .app_data(app_state.clone())
+            .wrap_fn(|req, srv| {
+                let method = req.method().to_string();
+                let route = req
+                    .match_pattern()
+                    .unwrap_or_else(|| "non_routée".to_string());
+                track_request(method, route, srv.call(req))
+            })
+            // Enregistré en dernier : englobe les autres middlewares et les handlers
+            .wrap_fn(|req, srv| {
+                let request_id = req
+                    .headers()
+                    .get(REQUEST_ID_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.to_string());
+                let method = req.method().to_string();
+                let route = req
+                    .match_pattern()
+                    .unwrap_or_else(|| "non_routée".to_string());
+                trace_request(request_id, method, route, srv.call(req))
+            })
             .service(push_component)
             .service(get_component)
             .service(update_component)
             .service(delete_component)
-    })
-    .bind(("127.0.0.1", 8080))?
-    .run()
-    .await
+            .service(patch_component)
+            .service(get_component_history)
+            .service(rollback_component)
+            .service(delete_all_components)
+            .service(list_components)
+            .service(list_trashed_components)
+            .service(restore_component)
+            .service(run_garbage_collection)
+            .service(get_garbage_collection)
+            .service(get_retention_report)
+            .service(run_retention)
+            .service(get_audit_log)
+            .service(list_webhook_deliveries)
+            .service(stream_events)
+            .service(get_metrics)
+            .service(get_health)
+            .service(get_readiness)
+            .service(get_job)
+    });
+
+    let address = (server_config.host.clone(), server_config.port);
+    let server = match certificate {
+        Some(certificate) => {
+            let tls_config = tls::server_config(certificate).map_err(std::io::Error::other)?;
+            info!(
+                "Serveur démarré sur https://{}:{}",
+                server_config.host, server_config.port
+            );
+            server.bind_rustls_0_23(address, tls_config)?
+        }
+        None => {
+            info!(
+                "Serveur démarré sur http://{}:{}",
+                server_config.host, server_config.port
+            );
+            server.bind(address)?
+        }
+    };
+    server.run().await
 }
@@ -0,0 +1,156 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::entities::{Config, Descriptor, Layer, Manifest, ManifestMetadata};
+use crate::services::calculate_sha256;
+use crate::upload::Attachment;
+
+pub const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+pub const INDEX_MEDIA_TYPE: &str = "application/vnd.oci.image.index.v1+json";
+pub const CONFIG_MEDIA_TYPE: &str = "application/vnd.wasm.config.v0+json";
+pub const WASM_LAYER_MEDIA_TYPE: &str = "application/wasm";
+pub const COMPONENT_TYPE_ANNOTATION: &str = "com.aneocorp.component.type";
+
+// JSON canonique : clés d'objet triées (ordre des octets UTF-8) à tous les niveaux, sans
+// espaces ; des entrées identiques donnent les mêmes octets, donc le même digest
+pub fn canonical_json<T: Serialize>(value: &T) -> Vec<u8> {
+    let value = serde_json::to_value(value).expect("valeur toujours sérialisable");
+    let mut out = Vec::new();
+    write_canonical(&value, &mut out);
+    out
+}
+
+fn write_canonical(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_by_key(|(key, _)| *key);
+            out.push(b'{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                out.extend(serde_json::to_vec(key).expect("clé toujours sérialisable"));
+                out.push(b':');
+                write_canonical(value, out);
+            }
+            out.push(b'}');
+        }
+        Value::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(b']');
+        }
+        scalar => out.extend(serde_json::to_vec(scalar).expect("valeur toujours sérialisable")),
+    }
+}
+
+// Contenu du blob de config : le CRD en JSON canonique
+pub fn config_content(metadata: &ManifestMetadata) -> Vec<u8> {
+    canonical_json(metadata)
+}
+
+pub fn config_descriptor(config_content: &[u8]) -> Config {
+    Config {
+        media_type: CONFIG_MEDIA_TYPE.to_string(),
+        size: config_content.len() as i64,
+        digest: calculate_sha256(config_content),
+    }
+}
+
+pub fn wasm_layer(wasm_content: &[u8]) -> Layer {
+    wasm_layer_of(calculate_sha256(wasm_content), wasm_content.len() as u64)
+}
+
+// Couche d'un binaire dont le digest a été calculé à la réception (voir `upload::SpooledWasm`)
+pub fn wasm_layer_of(digest: String, size: u64) -> Layer {
+    Layer {
+        media_type: WASM_LAYER_MEDIA_TYPE.to_string(),
+        size: size as i64,
+        digest,
+        annotations: None,
+    }
+}
+
+// Pièce jointe : couche supplémentaire, nommée par l'annotation de titre OCI
+pub fn attachment_layer(attachment: &Attachment) -> Layer {
+    let mut annotations = Map::new();
+    annotations.insert(
+        "org.opencontainers.image.title".to_string(),
+        Value::String(attachment.name.clone()),
+    );
+    Layer {
+        media_type: attachment.media_type.clone(),
+        size: attachment.content.len() as i64,
+        digest: calculate_sha256(&attachment.content),
+        annotations: Some(annotations),
+    }
+}
+
+// Descripteur d'un manifest (ou index) tel qu'il est envoyé au registre
+pub fn manifest_descriptor(media_type: &str, body: &[u8]) -> Descriptor {
+    Descriptor {
+        media_type: media_type.to_string(),
+        size: body.len() as i64,
+        digest: calculate_sha256(body),
+        annotations: None,
+    }
+}
+
+pub fn build_annotations(
+    metadata: &ManifestMetadata,
+    created: DateTime<Utc>,
+) -> Map<String, Value> {
+    let crd_annotations = &metadata.metadata.annotations;
+    let mut annotations = Map::new();
+
+    let mut insert = |key: &str, value: &str| {
+        annotations.insert(key.to_string(), Value::String(value.to_string()));
+    };
+
+    insert("org.opencontainers.image.title", &metadata.metadata.name);
+    insert(
+        "org.opencontainers.image.description",
+        &crd_annotations.description,
+    );
+    insert("org.opencontainers.image.version", &crd_annotations.version);
+    insert("org.opencontainers.image.created", &created.to_rfc3339());
+    insert("org.opencontainers.image.architecture", "wasm");
+    insert("org.opencontainers.image.os", "any");
+    if let Some(label) = &crd_annotations.label {
+        insert("org.opencontainers.image.label", label);
+    }
+    if let Some(icon) = &crd_annotations.icon {
+        insert("org.opencontainers.image.icon", icon);
+    }
+    if let Some(color) = &crd_annotations.color {
+        insert("org.opencontainers.image.color", color);
+    }
+    if let Some(ui) = &crd_annotations.ui {
+        insert("org.opencontainers.image.ui", ui);
+    }
+    insert(COMPONENT_TYPE_ANNOTATION, &metadata.spec.type_field);
+
+    annotations
+}
+
+pub fn build_manifest(
+    metadata: &ManifestMetadata,
+    config: Config,
+    layers: Vec<Layer>,
+    created: DateTime<Utc>,
+) -> Manifest {
+    Manifest {
+        schema_version: 2,
+        media_type: MANIFEST_MEDIA_TYPE.to_string(),
+        config,
+        layers,
+        annotations: Some(Value::Object(build_annotations(metadata, created))),
+    }
+}
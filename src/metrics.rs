@@ -0,0 +1,146 @@
+use actix_web::dev::ServiceResponse;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use std::future::Future;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+
+// Les helpers du registre n'ont pas accès à l'état de l'application : les métriques sont globales
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    http_in_flight: IntGauge,
+    upstream_duration: HistogramVec,
+    upstream_errors: IntCounterVec,
+    uploaded_bytes: IntCounter,
+    downloaded_bytes: IntCounter,
+}
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "Requêtes HTTP traitées"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new("http_request_duration_seconds", "Durée des requêtes HTTP"),
+            &["method", "route", "status"],
+        )
+        .unwrap();
+        let http_in_flight =
+            IntGauge::new("http_requests_in_flight", "Requêtes HTTP en cours").unwrap();
+        let upstream_duration = HistogramVec::new(
+            HistogramOpts::new("zot_request_duration_seconds", "Durée des appels à Zot"),
+            &["operation"],
+        )
+        .unwrap();
+        let upstream_errors = IntCounterVec::new(
+            Opts::new("zot_request_errors_total", "Appels à Zot en erreur"),
+            &["operation"],
+        )
+        .unwrap();
+        let uploaded_bytes =
+            IntCounter::new("zot_uploaded_bytes_total", "Octets envoyés à Zot").unwrap();
+        let downloaded_bytes =
+            IntCounter::new("zot_downloaded_bytes_total", "Octets reçus de Zot").unwrap();
+
+        registry.register(Box::new(http_requests.clone())).unwrap();
+        registry.register(Box::new(http_duration.clone())).unwrap();
+        registry.register(Box::new(http_in_flight.clone())).unwrap();
+        registry
+            .register(Box::new(upstream_duration.clone()))
+            .unwrap();
+        registry
+            .register(Box::new(upstream_errors.clone()))
+            .unwrap();
+        registry.register(Box::new(uploaded_bytes.clone())).unwrap();
+        registry
+            .register(Box::new(downloaded_bytes.clone()))
+            .unwrap();
+
+        Metrics {
+            registry,
+            http_requests,
+            http_duration,
+            http_in_flight,
+            upstream_duration,
+            upstream_errors,
+            uploaded_bytes,
+            downloaded_bytes,
+        }
+    }
+
+    pub fn observe_upstream(&self, operation: &str, duration: Duration, failed: bool) {
+        self.upstream_duration
+            .with_label_values(&[operation])
+            .observe(duration.as_secs_f64());
+        if failed {
+            self.upstream_errors.with_label_values(&[operation]).inc();
+        }
+    }
+
+    pub fn uploaded(&self, bytes: usize) {
+        self.uploaded_bytes.inc_by(bytes as u64);
+    }
+
+    pub fn downloaded(&self, bytes: usize) {
+        self.downloaded_bytes.inc_by(bytes as u64);
+    }
+
+    pub fn render(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("encodage texte des métriques");
+        buffer
+    }
+}
+
+// Requête comptée en cours jusqu'à sa destruction, y compris quand le client se déconnecte et que
+// la requête est abandonnée avant sa réponse
+struct InFlight<'a>(&'a IntGauge);
+
+impl<'a> InFlight<'a> {
+    fn start(gauge: &'a IntGauge) -> Self {
+        gauge.inc();
+        InFlight(gauge)
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+// Mesure une requête HTTP du service ; `route` est le motif de la ressource, pas le chemin
+pub async fn track_request<B>(
+    method: String,
+    route: String,
+    response: impl Future<Output = Result<ServiceResponse<B>, actix_web::Error>>,
+) -> Result<ServiceResponse<B>, actix_web::Error> {
+    let metrics = &*METRICS;
+    let in_flight = InFlight::start(&metrics.http_in_flight);
+    let started = Instant::now();
+    let response = response.await;
+    drop(in_flight);
+
+    let status = match &response {
+        Ok(response) => response.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    let labels = [method.as_str(), route.as_str(), status.as_str()];
+    metrics.http_requests.with_label_values(&labels).inc();
+    metrics
+        .http_duration
+        .with_label_values(&labels)
+        .observe(started.elapsed().as_secs_f64());
+    response
+}
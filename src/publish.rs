@@ -0,0 +1,338 @@
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::Serialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use crate::entities::{
+    AppState, Config, Descriptor, Layer, Manifest, ManifestMetadata, Operation, RegistryCredentials,
+};
+use crate::history::{descriptor_of, tag_manifest};
+use crate::manifest_builder::{
+    attachment_layer, build_manifest, canonical_json, config_content, config_descriptor,
+    manifest_descriptor, wasm_layer, MANIFEST_MEDIA_TYPE,
+};
+use crate::resilience::ZotError;
+use crate::services::{
+    blob_exists, fetch_blob, fetch_manifest, fetch_manifest_raw, push_blob, BlobContent, Progress,
+};
+use crate::upload::{Attachment, SpooledWasm};
+
+// Le CRD est soit re-sérialisé et poussé, soit repris tel quel depuis le manifest courant
+pub enum ConfigSource {
+    Upload,
+    Existing(Config),
+}
+
+// Binaire téléversé, suivi des éventuelles pièces jointes ; `Spooled` relit le binaire, déjà
+// décrit par sa couche, depuis son fichier temporaire
+pub enum LayerSource<'a> {
+    Upload(&'a [u8], &'a [Attachment]),
+    Spooled(&'a SpooledWasm, &'a [Attachment]),
+    Existing(Vec<Layer>),
+}
+
+// Blob à pousser avant le manifest
+pub struct PlannedBlob<'a> {
+    pub media_type: String,
+    pub digest: String,
+    pub size: i64,
+    pub content: BlobContent<'a>,
+}
+
+// Manifest exact et blobs à pousser, calculés sans aucun accès au registre
+pub struct PublishPlan<'a> {
+    pub manifest: Manifest,
+    pub body: Vec<u8>,
+    pub blobs: Vec<PlannedBlob<'a>>,
+}
+
+// Date de création fournie par l'appelant (secondes depuis l'epoch, comme SOURCE_DATE_EPOCH)
+// pour un manifest reproductible ; l'heure courante sinon
+pub fn creation_time(source_date_epoch: Option<i64>) -> Result<DateTime<Utc>, String> {
+    match source_date_epoch {
+        Some(seconds) => DateTime::from_timestamp(seconds, 0)
+            .ok_or_else(|| format!("source_date_epoch invalide: {}", seconds)),
+        None => Ok(Utc::now()),
+    }
+}
+
+// Couches du binaire et des pièces jointes, chacune ajoutée aux blobs à pousser
+fn plan_layers<'a>(
+    blobs: &mut Vec<PlannedBlob<'a>>,
+    wasm: Layer,
+    wasm_content: BlobContent<'a>,
+    attachments: &'a [Attachment],
+) -> Vec<Layer> {
+    let mut uploaded = vec![(wasm, wasm_content)];
+    uploaded.extend(attachments.iter().map(|attachment| {
+        (
+            attachment_layer(attachment),
+            BlobContent::Memory(Cow::Borrowed(attachment.content.as_slice())),
+        )
+    }));
+    uploaded
+        .into_iter()
+        .map(|(layer, content)| {
+            blobs.push(PlannedBlob {
+                media_type: layer.media_type.clone(),
+                digest: layer.digest.clone(),
+                size: layer.size,
+                content,
+            });
+            layer
+        })
+        .collect()
+}
+
+pub fn plan_component<'a>(
+    metadata: &ManifestMetadata,
+    config: ConfigSource,
+    layers: LayerSource<'a>,
+    created: DateTime<Utc>,
+) -> Result<PublishPlan<'a>, String> {
+    let mut blobs = Vec::new();
+
+    let layers = match layers {
+        LayerSource::Upload(wasm_content, attachments) => plan_layers(
+            &mut blobs,
+            wasm_layer(wasm_content),
+            BlobContent::Memory(Cow::Borrowed(wasm_content)),
+            attachments,
+        ),
+        LayerSource::Spooled(wasm, attachments) => plan_layers(
+            &mut blobs,
+            wasm.layer.clone(),
+            BlobContent::File(wasm.file.path()),
+            attachments,
+        ),
+        LayerSource::Existing(layers) => layers,
+    };
+
+    let config = match config {
+        ConfigSource::Upload => {
+            let config_content = config_content(metadata);
+            let config = config_descriptor(&config_content);
+            blobs.push(PlannedBlob {
+                media_type: config.media_type.clone(),
+                digest: config.digest.clone(),
+                size: config.size,
+                content: BlobContent::Memory(Cow::Owned(config_content)),
+            });
+            config
+        }
+        ConfigSource::Existing(config) => config,
+    };
+
+    let manifest = build_manifest(metadata, config, layers, created);
+    let body = canonical_json(&manifest);
+    Ok(PublishPlan {
+        manifest,
+        body,
+        blobs,
+    })
+}
+
+// Pousse les blobs nécessaires puis tague le manifest ; retourne le descripteur du manifest
+#[allow(clippy::too_many_arguments)]
+pub async fn publish_component(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    reference: &str,
+    metadata: &ManifestMetadata,
+    config: ConfigSource,
+    layers: LayerSource<'_>,
+    operation: Operation,
+    created: DateTime<Utc>,
+) -> Result<Descriptor, ZotError> {
+    let plan = plan_component(metadata, config, layers, created)?;
+    push_plan(
+        state,
+        credentials,
+        repository,
+        reference,
+        plan,
+        operation,
+        |_| Arc::new(|_| {}),
+    )
+    .await
+}
+
+// Exécute un plan de publication ; `progress` fournit, pour chaque blob, le suivi des octets
+// transmis à Zot
+pub async fn push_plan(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    reference: &str,
+    plan: PublishPlan<'_>,
+    operation: Operation,
+    progress: impl Fn(&PlannedBlob) -> Progress,
+) -> Result<Descriptor, ZotError> {
+    let progress = &progress;
+    let client = state.registry_client.lock().unwrap().clone();
+    let client = &client;
+    let zot = &state.zot_config;
+
+    // Les blobs sont indépendants : envoyés en parallèle (borné) ; au premier échec les envois
+    // en cours sont abandonnés et le manifest n'est pas écrit
+    let mut uploads = stream::iter(&plan.blobs)
+        .map(|blob| async move {
+            push_blob(
+                client,
+                &zot.url,
+                repository,
+                credentials,
+                &blob.content,
+                &blob.digest,
+                progress(blob),
+            )
+            .await
+            .map(|_| blob)
+        })
+        .buffer_unordered(state.upload_config.max_parallel_blobs.max(1));
+    while let Some(blob) = uploads.try_next().await? {
+        state
+            .blob_ledger
+            .record(repository, &blob.digest, blob.size as u64);
+    }
+
+    tag_manifest(
+        state,
+        credentials,
+        repository,
+        reference,
+        MANIFEST_MEDIA_TYPE,
+        plan.body,
+        operation,
+    )
+    .await
+}
+
+#[derive(Serialize)]
+pub struct PlannedBlobReport {
+    pub media_type: String,
+    pub digest: String,
+    pub size: i64,
+    // Contenu fourni par la requête (sinon repris du manifest courant)
+    pub uploaded: bool,
+    // Déjà présent dans le dépôt : il ne serait pas renvoyé
+    pub exists: bool,
+}
+
+#[derive(Serialize)]
+pub struct TagReport {
+    pub exists: bool,
+    pub current_digest: Option<String>,
+    // Le tag pointe déjà sur un manifest identique
+    pub unchanged: bool,
+}
+
+#[derive(Serialize)]
+pub struct DryRunReport {
+    pub repository: String,
+    pub reference: String,
+    pub manifest_digest: String,
+    pub manifest_size: i64,
+    pub manifest: Manifest,
+    pub blobs: Vec<PlannedBlobReport>,
+    pub tag: TagReport,
+}
+
+// Ce que publierait `publish_component`, sans rien écrire dans le registre
+pub async fn dry_run_component(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    reference: &str,
+    plan: PublishPlan<'_>,
+) -> Result<DryRunReport, ZotError> {
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+
+    let descriptor = manifest_descriptor(MANIFEST_MEDIA_TYPE, &plan.body);
+    let referenced = std::iter::once((
+        &plan.manifest.config.media_type,
+        &plan.manifest.config.digest,
+        plan.manifest.config.size,
+    ))
+    .chain(
+        plan.manifest
+            .layers
+            .iter()
+            .map(|layer| (&layer.media_type, &layer.digest, layer.size)),
+    );
+
+    let mut blobs = Vec::new();
+    for (media_type, digest, size) in referenced {
+        blobs.push(PlannedBlobReport {
+            media_type: media_type.clone(),
+            digest: digest.clone(),
+            size,
+            uploaded: plan.blobs.iter().any(|blob| &blob.digest == digest),
+            exists: blob_exists(&client, &zot.url, repository, digest, credentials).await?,
+        });
+    }
+
+    let current_digest = fetch_manifest_raw(&client, &zot.url, repository, reference, credentials)
+        .await?
+        .map(|raw| descriptor_of(&raw).digest);
+
+    Ok(DryRunReport {
+        repository: repository.to_string(),
+        reference: reference.to_string(),
+        manifest_digest: descriptor.digest.clone(),
+        manifest_size: descriptor.size,
+        manifest: plan.manifest,
+        blobs,
+        tag: TagReport {
+            exists: current_digest.is_some(),
+            unchanged: current_digest.as_deref() == Some(descriptor.digest.as_str()),
+            current_digest,
+        },
+    })
+}
+
+pub struct CurrentComponent {
+    pub manifest: Manifest,
+    pub digest: String,
+    pub crd: Value,
+}
+
+// Manifest actuellement tagué et CRD stocké dans son blob de config
+pub async fn fetch_current_component(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    reference: &str,
+) -> Result<Option<CurrentComponent>, ZotError> {
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+
+    let (manifest, digest) =
+        match fetch_manifest::<Manifest>(&client, &zot.url, repository, reference, credentials)
+            .await?
+        {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+
+    let config = fetch_blob(
+        &client,
+        &zot.url,
+        repository,
+        &manifest.config.digest,
+        credentials,
+    )
+    .await?;
+    let crd = serde_json::from_slice(&config)
+        .map_err(|e| format!("Erreur parsing config JSON: {}", e))?;
+
+    Ok(Some(CurrentComponent {
+        manifest,
+        digest,
+        crd,
+    }))
+}
@@ -0,0 +1,359 @@
+use actix_web::HttpResponse;
+use reqwest::redirect::Policy;
+use reqwest::{Certificate, Client, Identity, Method, Request, Response, StatusCode};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::sync::{LazyLock, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::config::UpstreamConfig;
+
+static CONFIG: OnceLock<UpstreamConfig> = OnceLock::new();
+
+const HTTP_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Un disjoncteur par upstream (schéma, hôte et port)
+static BREAKERS: LazyLock<Mutex<HashMap<String, Breaker>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub fn configure(config: UpstreamConfig) {
+    let _ = CONFIG.set(config);
+}
+
+fn config() -> &'static UpstreamConfig {
+    CONFIG.get_or_init(UpstreamConfig::default)
+}
+
+// Client réservé à Zot : le bundle CA, l'identité mTLS et `insecure_skip_verify` ne valent
+// que pour le registre
+pub fn build_client(config: &UpstreamConfig) -> Result<Client, String> {
+    // Pas de délai global sur le client : il est posé par requête (voir `request_timeout`), celui
+    // des envois de blobs dépendant de leur taille (voir `blob_timeout`)
+    let mut builder = Client::builder()
+        .connect_timeout(Duration::from_secs(config.connect_timeout_seconds.max(1)));
+
+    if let Some(path) = &config.ca_bundle {
+        let pem = fs::read(path).map_err(|e| format!("Erreur lecture CA {}: {}", path, e))?;
+        let certificates = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("Bundle CA invalide {}: {}", path, e))?;
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    match (&config.client_cert, &config.client_key) {
+        (Some(cert_path), Some(key_path)) => {
+            let cert = fs::read(cert_path)
+                .map_err(|e| format!("Erreur lecture certificat client {}: {}", cert_path, e))?;
+            let key = fs::read(key_path)
+                .map_err(|e| format!("Erreur lecture clé client {}: {}", key_path, e))?;
+            let identity = Identity::from_pkcs8_pem(&cert, &key)
+                .map_err(|e| format!("Identité client invalide: {}", e))?;
+            builder = builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => return Err("client_cert et client_key doivent être fournis ensemble".to_string()),
+    }
+
+    if config.insecure_skip_verify {
+        tracing::warn!("Vérification du certificat de Zot désactivée (insecure_skip_verify)");
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    builder
+        .build()
+        .map_err(|e| format!("Erreur construction client HTTP: {}", e))
+}
+
+// Client des appels hors Zot (JWKS de l'IdP, webhooks, téléchargements) : racines de confiance
+// du système, sans certificat client. Aucune redirection n'est suivie : un hôte autorisé ne
+// doit pas pouvoir renvoyer la requête vers une autre adresse
+pub fn build_http_client() -> Result<Client, String> {
+    Client::builder()
+        .connect_timeout(HTTP_CONNECT_TIMEOUT)
+        .timeout(HTTP_TIMEOUT)
+        .redirect(Policy::none())
+        .build()
+        .map_err(|e| format!("Erreur construction client HTTP: {}", e))
+}
+
+#[derive(Debug)]
+pub enum UpstreamError {
+    // Disjoncteur ouvert : l'appel n'a pas été tenté
+    CircuitOpen(String),
+    Transport(reqwest::Error),
+}
+
+impl UpstreamError {
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, UpstreamError::Transport(e) if e.is_timeout())
+    }
+}
+
+impl fmt::Display for UpstreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpstreamError::CircuitOpen(upstream) => {
+                write!(f, "Zot indisponible ({}): circuit ouvert", upstream)
+            }
+            UpstreamError::Transport(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    // Disjoncteur ouvert : Zot n'a pas été appelé
+    Unavailable,
+    // Connexion impossible, délai dépassé ou 429/502/503/504 : une nouvelle tentative peut réussir
+    Transient,
+    Other,
+}
+
+// Erreur d'un appel à Zot, dont la nature décide du statut renvoyé et des reprises
+#[derive(Debug, Clone)]
+pub struct ZotError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl ZotError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        ZotError {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    // Réponse de Zot en erreur
+    pub fn status(context: &str, status: StatusCode) -> Self {
+        let kind = if is_transient_status(status) {
+            ErrorKind::Transient
+        } else {
+            ErrorKind::Other
+        };
+        ZotError::new(kind, format!("{}: {}", context, status))
+    }
+
+    // Appel à Zot en échec, avant toute réponse
+    pub fn upstream(context: &str, error: UpstreamError) -> Self {
+        let kind = match &error {
+            UpstreamError::CircuitOpen(_) => ErrorKind::Unavailable,
+            UpstreamError::Transport(e) if e.is_connect() || e.is_timeout() => ErrorKind::Transient,
+            UpstreamError::Transport(_) => ErrorKind::Other,
+        };
+        ZotError::new(kind, format!("{}: {}", context, error))
+    }
+
+    pub fn context(self, context: &str) -> Self {
+        ZotError::new(self.kind, format!("{}: {}", context, self.message))
+    }
+
+    pub fn is_transient(&self) -> bool {
+        self.kind == ErrorKind::Transient
+    }
+}
+
+impl From<String> for ZotError {
+    fn from(message: String) -> Self {
+        ZotError::new(ErrorKind::Other, message)
+    }
+}
+
+impl fmt::Display for ZotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+// État du disjoncteur d'un upstream : fermé, ouvert jusqu'à `open_until`, puis semi-ouvert le
+// temps d'un appel d'essai
+#[derive(Default)]
+pub struct Breaker {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl Breaker {
+    pub fn is_open(&self, now: Instant) -> bool {
+        self.open_until.is_some_and(|until| now < until)
+    }
+
+    // Refuse l'appel tant que le disjoncteur est ouvert ; une fois le délai écoulé, un seul appel
+    // d'essai passe et les suivants échouent jusqu'à son résultat
+    pub fn acquire(&mut self, config: &UpstreamConfig, now: Instant) -> bool {
+        match self.open_until {
+            Some(until) if now < until => false,
+            Some(_) => {
+                self.open_until = Some(now + Duration::from_secs(config.open_seconds.max(1)));
+                true
+            }
+            None => true,
+        }
+    }
+
+    // Retourne true quand l'échec ouvre un disjoncteur jusque-là fermé
+    pub fn record(&mut self, config: &UpstreamConfig, failed: bool, now: Instant) -> bool {
+        if !failed {
+            *self = Breaker::default();
+            return false;
+        }
+        self.consecutive_failures += 1;
+        if self.consecutive_failures < config.failure_threshold.max(1) {
+            return false;
+        }
+        let opened = self.open_until.is_none();
+        self.open_until = Some(now + Duration::from_secs(config.open_seconds.max(1)));
+        opened
+    }
+}
+
+fn upstream_key(request: &Request) -> String {
+    let url = request.url();
+    format!(
+        "{}://{}:{}",
+        url.scheme(),
+        url.host_str().unwrap_or_default(),
+        url.port_or_known_default().unwrap_or_default()
+    )
+}
+
+fn acquire(upstream: &str) -> Result<(), UpstreamError> {
+    let mut breakers = BREAKERS.lock().unwrap();
+    let breaker = breakers.entry(upstream.to_string()).or_default();
+    if breaker.acquire(config(), Instant::now()) {
+        Ok(())
+    } else {
+        Err(UpstreamError::CircuitOpen(upstream.to_string()))
+    }
+}
+
+fn record(upstream: &str, failed: bool) {
+    let mut breakers = BREAKERS.lock().unwrap();
+    let breaker = breakers.entry(upstream.to_string()).or_default();
+    if breaker.record(config(), failed, Instant::now()) {
+        tracing::warn!(upstream, "disjoncteur ouvert");
+    }
+}
+
+// Erreur côté Zot, comptée par le disjoncteur ; les 4xx sont des réponses valides
+fn is_failure(result: &Result<Response, reqwest::Error>) -> bool {
+    match result {
+        Ok(response) => response.status().is_server_error(),
+        Err(_) => true,
+    }
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+fn is_retryable(result: &Result<Response, reqwest::Error>) -> bool {
+    match result {
+        Ok(response) => is_transient_status(response.status()),
+        Err(e) => e.is_connect() || e.is_timeout(),
+    }
+}
+
+// GET/HEAD et PUT de manifest : rejouer la requête donne le même état. Le PUT d'un blob vise une
+// session d'upload à usage unique et n'est jamais rejoué tel quel (voir `services::push_blob`)
+fn is_idempotent(request: &Request) -> bool {
+    match *request.method() {
+        Method::GET | Method::HEAD => true,
+        Method::PUT => request.url().path().contains("/manifests/"),
+        _ => false,
+    }
+}
+
+// Délai exponentiel depuis `initial_backoff_ms`, plafonné à `max_backoff_ms`
+pub fn backoff(config: &UpstreamConfig, attempt: u32) -> Duration {
+    let delay = config
+        .initial_backoff_ms
+        .saturating_mul(1u64 << attempt.min(16));
+    Duration::from_millis(delay.min(config.max_backoff_ms))
+}
+
+// Délai avant la tentative suivante d'une opération reprise par l'appelant ; None une fois
+// `max_retries` atteint
+pub fn retry_delay(attempt: u32) -> Option<Duration> {
+    let config = config();
+    (attempt < config.max_retries).then(|| backoff(config, attempt))
+}
+
+// Délai total d'un appel à Zot hors envoi de blob
+pub fn request_timeout() -> Duration {
+    Duration::from_secs(config().timeout_seconds.max(1))
+}
+
+// Délai total de l'envoi d'un blob : celui d'un appel ordinaire, plus le temps de transférer
+// `size` octets au débit minimal accepté. Un Zot bloqué libère ainsi la tâche au lieu de la
+// retenir indéfiniment
+pub fn blob_timeout(config: &UpstreamConfig, size: u64) -> Duration {
+    let rate = config.min_blob_rate_kib.max(1).saturating_mul(1024);
+    Duration::from_secs(config.timeout_seconds.max(1)) + Duration::from_secs(size.div_ceil(rate))
+}
+
+// `blob_timeout` selon la configuration chargée
+pub fn upload_timeout(size: u64) -> Duration {
+    blob_timeout(config(), size)
+}
+
+// Exécute la requête derrière le disjoncteur de son upstream, avec reprises bornées pour les
+// opérations idempotentes
+pub async fn execute(
+    client: &Client,
+    request: Request,
+    retry: bool,
+) -> Result<Response, UpstreamError> {
+    let upstream = upstream_key(&request);
+    let max_retries = if retry && is_idempotent(&request) {
+        config().max_retries
+    } else {
+        0
+    };
+
+    let mut attempt = 0;
+    let mut request = request;
+    loop {
+        acquire(&upstream)?;
+        // Corps en mémoire : la copie n'échoue que pour un flux, qui n'est alors pas rejoué
+        let retry = request.try_clone().filter(|_| attempt < max_retries);
+        let result = client.execute(request).await;
+        record(&upstream, is_failure(&result));
+
+        match retry {
+            Some(next) if is_retryable(&result) => {
+                let delay = backoff(config(), attempt);
+                tracing::debug!(
+                    upstream,
+                    attempt = attempt + 1,
+                    delay_ms = delay.as_millis() as u64,
+                    "nouvelle tentative vers Zot"
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+                request = next;
+            }
+            _ => return result.map_err(UpstreamError::Transport),
+        }
+    }
+}
+
+// 503 quand le disjoncteur a court-circuité l'appel à Zot, 500 sinon
+pub fn error_response(error: impl Into<ZotError>) -> HttpResponse {
+    let error = error.into();
+    match error.kind {
+        ErrorKind::Unavailable => HttpResponse::ServiceUnavailable()
+            .insert_header(("Retry-After", config().open_seconds.max(1).to_string()))
+            .body(error.message),
+        _ => HttpResponse::InternalServerError().body(error.message),
+    }
+}
@@ -0,0 +1,236 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::audit::{audited_task, AuditAction};
+use crate::config::RetentionPolicy;
+use crate::deletion::{is_internal_tag, tagged_manifests};
+use crate::entities::{AppState, RegistryCredentials};
+use crate::history::{history_tag, recorded_at};
+use crate::resilience::ZotError;
+use crate::services::{glob_match, list_repositories};
+use crate::trash::{trash_version, TrashError};
+
+// Acteur consigné dans le journal d'audit pour les versions mises en corbeille
+const RETENTION_ACTOR: &str = "retention";
+
+#[derive(Serialize)]
+pub struct TagDecision {
+    pub tag: String,
+    pub published: Option<DateTime<Utc>>,
+    pub reason: String,
+}
+
+#[derive(Serialize, Default)]
+pub struct RepositoryRetention {
+    pub repository: String,
+    pub kept: Vec<TagDecision>,
+    pub deleted: Vec<TagDecision>,
+}
+
+#[derive(Serialize)]
+pub struct RetentionReport {
+    pub dry_run: bool,
+    pub repositories: Vec<RepositoryRetention>,
+}
+
+// `published` est la date à laquelle le tag a été posé sur ce manifest, tirée de son
+// historique : la date `created` du manifest est fournie par l'appelant (build reproductible)
+// et ne dit rien de l'âge de la version dans le registre
+pub struct Version {
+    pub tag: String,
+    pub digest: String,
+    pub published: Option<DateTime<Utc>>,
+}
+
+type VersionNumber = (u64, u64, u64);
+
+// Tag de la forme MAJEUR.MINEUR.CORRECTIF (préfixe `v` et métadonnées `+build` admis) : numéro
+// de version et suffixe de pré-version (1.2.0-rc.1). None pour un tag hors semver (latest, main…),
+// que la rétention ne supprime jamais
+fn semver(tag: &str) -> Option<(VersionNumber, Option<&str>)> {
+    let tag = tag.strip_prefix('v').unwrap_or(tag);
+    let tag = tag.split_once('+').map_or(tag, |(version, _)| version);
+    let (core, prerelease) = match tag.split_once('-') {
+        Some((_, "")) => return None,
+        Some((core, prerelease)) => (core, Some(prerelease)),
+        None => (tag, None),
+    };
+    let numbers: Vec<u64> = core
+        .split('.')
+        .map(|part| {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            part.parse().ok()
+        })
+        .collect::<Option<_>>()?;
+    match numbers[..] {
+        [major, minor, patch] => Some(((major, minor, patch), prerelease)),
+        _ => None,
+    }
+}
+
+fn policy_for<'a>(state: &'a AppState, repository: &str) -> Option<&'a RetentionPolicy> {
+    state
+        .retention_config
+        .policies
+        .iter()
+        .find(|policy| glob_match(&policy.repositories, repository))
+}
+
+pub fn evaluate(
+    policy: &RetentionPolicy,
+    repository: &str,
+    versions: Vec<Version>,
+    now: DateTime<Utc>,
+) -> RepositoryRetention {
+    let protected_digests: HashSet<&str> = versions
+        .iter()
+        .filter(|v| policy.protected_tags.iter().any(|p| glob_match(p, &v.tag)))
+        .map(|v| v.digest.as_str())
+        .collect();
+
+    // Les releases publiées le plus récemment d'abord ; à date égale, la plus haute version
+    let mut releases: Vec<(&Version, VersionNumber)> = versions
+        .iter()
+        .filter(|v| v.published.is_some())
+        .filter_map(|v| match semver(&v.tag) {
+            Some((number, None)) => Some((v, number)),
+            _ => None,
+        })
+        .collect();
+    releases.sort_by(|(a, a_number), (b, b_number)| {
+        b.published
+            .cmp(&a.published)
+            .then_with(|| b_number.cmp(a_number))
+    });
+    // Plusieurs tags d'un même manifest (1.2.0 et v1.2.0) comptent pour une seule release
+    let keep_last_releases = policy.keep_last_releases.unwrap_or(usize::MAX);
+    let mut recent_digests: Vec<&str> = Vec::new();
+    for (release, _) in &releases {
+        if recent_digests.contains(&release.digest.as_str()) {
+            continue;
+        }
+        if recent_digests.len() >= keep_last_releases {
+            break;
+        }
+        recent_digests.push(&release.digest);
+    }
+
+    let mut report = RepositoryRetention {
+        repository: repository.to_string(),
+        ..Default::default()
+    };
+
+    for version in &versions {
+        let decision = |reason: &str| TagDecision {
+            tag: version.tag.clone(),
+            published: version.published,
+            reason: reason.to_string(),
+        };
+
+        if protected_digests.contains(version.digest.as_str()) {
+            report.kept.push(decision("référencé par un tag protégé"));
+            continue;
+        }
+        match (semver(&version.tag), version.published) {
+            (None, _) => report.kept.push(decision("tag hors semver")),
+            (Some(_), None) => report.kept.push(decision("date de publication inconnue")),
+            (Some((_, None)), Some(_)) => {
+                if recent_digests.contains(&version.digest.as_str()) {
+                    report.kept.push(decision("parmi les dernières releases"));
+                } else {
+                    report
+                        .deleted
+                        .push(decision("au-delà des dernières releases"));
+                }
+            }
+            (Some((_, Some(_))), Some(published)) => match policy.prerelease_max_age_days {
+                Some(days) if now - published > Duration::days(days) => {
+                    report.deleted.push(decision("pré-version expirée"));
+                }
+                _ => report.kept.push(decision("pré-version récente")),
+            },
+        }
+    }
+
+    report
+}
+
+// Évalue les politiques sur chaque dépôt concerné et, hors dry-run, passe en corbeille
+// les versions à supprimer
+pub async fn apply_retention(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: Option<&str>,
+    dry_run: bool,
+) -> Result<RetentionReport, ZotError> {
+    let repositories = match repository {
+        Some(repository) => vec![repository.to_string()],
+        None => {
+            let client = state.registry_client.lock().unwrap().clone();
+            let zot = &state.zot_config;
+            list_repositories(&client, &zot.url, credentials).await?
+        }
+    };
+
+    let now = Utc::now();
+    let mut report = RetentionReport {
+        dry_run,
+        repositories: Vec::new(),
+    };
+
+    for repository in repositories {
+        let Some(policy) = policy_for(state, &repository) else {
+            continue;
+        };
+
+        let (histories, tagged): (Vec<_>, Vec<_>) =
+            tagged_manifests(state, credentials, &repository)
+                .await?
+                .into_iter()
+                .partition(|m| is_internal_tag(&m.tag));
+        let histories: HashMap<String, Vec<u8>> =
+            histories.into_iter().map(|m| (m.tag, m.raw)).collect();
+        let versions = tagged
+            .into_iter()
+            .map(|m| Version {
+                published: histories
+                    .get(&history_tag(&m.tag))
+                    .and_then(|index| recorded_at(index, &m.digest)),
+                tag: m.tag,
+                digest: m.digest,
+            })
+            .collect();
+
+        let evaluation = evaluate(policy, &repository, versions, now);
+        if !dry_run {
+            for decision in &evaluation.deleted {
+                let trashed = audited_task(
+                    state,
+                    RETENTION_ACTOR,
+                    credentials,
+                    AuditAction::Trash,
+                    &repository,
+                    &decision.tag,
+                    &decision.tag,
+                    trash_version(state, credentials, &repository, &decision.tag),
+                )
+                .await;
+                match trashed {
+                    Err(TrashError::Registry(e)) => return Err(e),
+                    Err(TrashError::AlreadyTrashed) => tracing::warn!(
+                        repository,
+                        tag = decision.tag,
+                        "Autre version de même référence en corbeille: tag conservé"
+                    ),
+                    _ => {}
+                }
+            }
+        }
+        report.repositories.push(evaluation);
+    }
+
+    Ok(report)
+}
@@ -1,5 +1,23 @@
-use reqwest::Client;
+use futures::stream;
+use reqwest::{Body, Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
 use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::fs::File;
+use tokio::io::AsyncReadExt;
+use tracing::Instrument;
+
+use crate::entities::RegistryCredentials;
+use crate::manifest_builder::{INDEX_MEDIA_TYPE, MANIFEST_MEDIA_TYPE};
+use crate::metrics::METRICS;
+use crate::resilience::{
+    execute, request_timeout, retry_delay, upload_timeout, UpstreamError, ZotError,
+};
+use crate::telemetry::{current_request_id, REQUEST_ID_HEADER};
 
 pub fn calculate_sha256(data: &[u8]) -> String {
     let mut hasher = Sha256::new();
@@ -7,27 +25,149 @@ pub fn calculate_sha256(data: &[u8]) -> String {
     format!("sha256:{}", hex::encode(hasher.finalize()))
 }
 
+// Envoie une requête à Zot dans un span dédié, avec l'identifiant de la requête entrante ;
+// délais, reprises et disjoncteur sont appliqués par `resilience::execute`
+pub async fn send(operation: &str, request: RequestBuilder) -> Result<Response, UpstreamError> {
+    send_with(operation, request, true, Some(request_timeout())).await
+}
+
+// Sans reprise, pour les sondes qui doivent refléter l'état immédiat de Zot
+pub async fn send_once(
+    operation: &str,
+    request: RequestBuilder,
+) -> Result<Response, UpstreamError> {
+    send_with(operation, request, false, Some(request_timeout())).await
+}
+
+// Un délai posé sur la requête l'emporte sur `deadline` ; sans l'un ni l'autre, seul le délai de
+// connexion s'applique
+async fn send_with(
+    operation: &str,
+    request: RequestBuilder,
+    retry: bool,
+    deadline: Option<Duration>,
+) -> Result<Response, UpstreamError> {
+    let request = match current_request_id() {
+        Some(request_id) => request.header(REQUEST_ID_HEADER, request_id),
+        None => request,
+    };
+    let span = tracing::info_span!(
+        "zot",
+        operation,
+        status = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+    );
+
+    let started = Instant::now();
+    let (client, request) = request.build_split();
+    let response = match request {
+        Ok(mut request) => {
+            if request.timeout().is_none() {
+                *request.timeout_mut() = deadline;
+            }
+            execute(&client, request, retry)
+                .instrument(span.clone())
+                .await
+        }
+        Err(e) => Err(UpstreamError::Transport(e)),
+    };
+    let duration = started.elapsed();
+    span.record("duration_ms", duration.as_millis() as u64);
+
+    // Un 404 est une réponse attendue (référence absente), pas une erreur
+    let failed = match &response {
+        Ok(response) => {
+            span.record("status", response.status().as_u16());
+            !response.status().is_success() && response.status() != StatusCode::NOT_FOUND
+        }
+        Err(e) => {
+            span.in_scope(|| tracing::warn!(error = %e, "appel à Zot en échec"));
+            true
+        }
+    };
+    METRICS.observe_upstream(operation, duration, failed);
+    response
+}
+
+// Contenu d'un blob à pousser : en mémoire, ou lu en flux depuis le fichier temporaire d'un
+// upload pour ne pas garder le binaire en mémoire
+pub enum BlobContent<'a> {
+    Memory(Cow<'a, [u8]>),
+    File(&'a Path),
+}
+
+// Reçoit le nombre d'octets transmis depuis le début de l'envoi en cours
+pub type Progress = Arc<dyn Fn(u64) + Send + Sync>;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+impl BlobContent<'_> {
+    // Corps de la requête et sa taille ; un fichier est relu à chaque tentative
+    async fn body(&self, progress: Progress) -> Result<(Body, u64), ZotError> {
+        match self {
+            BlobContent::Memory(content) => {
+                let size = content.len() as u64;
+                progress(size);
+                Ok((Body::from(content.to_vec()), size))
+            }
+            BlobContent::File(path) => {
+                let file = File::open(path)
+                    .await
+                    .map_err(|e| format!("Erreur lecture {}: {}", path.display(), e))?;
+                let size = file
+                    .metadata()
+                    .await
+                    .map_err(|e| format!("Erreur lecture {}: {}", path.display(), e))?
+                    .len();
+                let chunks = stream::try_unfold((file, 0u64), move |(mut file, sent)| {
+                    let progress = progress.clone();
+                    async move {
+                        let mut chunk = vec![0; CHUNK_SIZE];
+                        let read = file.read(&mut chunk).await?;
+                        if read == 0 {
+                            return Ok::<_, std::io::Error>(None);
+                        }
+                        chunk.truncate(read);
+                        let sent = sent + read as u64;
+                        progress(sent);
+                        Ok(Some((chunk, (file, sent))))
+                    }
+                });
+                Ok((Body::wrap_stream(chunks), size))
+            }
+        }
+    }
+}
+
+// PUT monolithique vers la session ouverte par `init_upload` : délai proportionnel à la taille
+// du blob, et sans reprise, la session ne servant qu'une fois
 pub async fn upload_blob(
     client: &Client,
     url: &str,
-    username: &str,
-    password: &str,
-    content: &Vec<u8>,
+    credentials: &RegistryCredentials,
+    content: &BlobContent<'_>,
     digest: &str,
-) -> Result<(), String> {
-    let response = client
-        .put(url)
-        .basic_auth(username, Some(password))
-        .query(&[("digest", digest)])
-        .body(content.clone())
-        .header("Content-Type", "application/octet-stream")
-        .send()
-        .await
-        .map_err(|e| format!("Erreur upload: {}", e))?;
+    progress: Progress,
+) -> Result<(), ZotError> {
+    let (body, size) = content.body(progress).await?;
+    let response = send_with(
+        "upload_blob",
+        credentials
+            .apply(client.put(url))
+            .query(&[("digest", digest)])
+            .header("Content-Type", "application/octet-stream")
+            .header("Content-Length", size)
+            .body(body),
+        false,
+        Some(upload_timeout(size)),
+    )
+    .await
+    .map_err(|e| ZotError::upstream("Erreur upload", e))?;
 
     if !response.status().is_success() {
-        return Err(format!("Erreur statut: {}", response.status()));
+        return Err(ZotError::status("Erreur statut", response.status()));
     }
+    METRICS.uploaded(size as usize);
     Ok(())
 }
 
@@ -35,19 +175,15 @@ pub async fn init_upload(
     client: &Client,
     base_url: &str,
     name: &str,
-    username: &str,
-    password: &str,
-) -> Result<String, String> {
+    credentials: &RegistryCredentials,
+) -> Result<String, ZotError> {
     let init_url = format!("{}/v2/{}/blobs/uploads/", base_url, name);
-    let response = client
-        .post(&init_url)
-        .basic_auth(username, Some(password))
-        .send()
+    let response = send("init_upload", credentials.apply(client.post(&init_url)))
         .await
-        .map_err(|e| format!("Erreur init: {}", e))?;
+        .map_err(|e| ZotError::upstream("Erreur init", e))?;
 
     if !response.status().is_success() {
-        return Err(format!("Erreur statut init: {}", response.status()));
+        return Err(ZotError::status("Erreur statut init", response.status()));
     }
 
     let location = response
@@ -62,4 +198,351 @@ pub async fn init_upload(
     } else {
         format!("{}{}", base_url, location)
     })
-}
\ No newline at end of file
+}
+
+// Sur erreur transitoire, l'envoi repart d'une nouvelle session ; Zot a pu recevoir le blob
+// avant que la réponse ne se perde, d'où la vérification préalable par HEAD
+pub async fn push_blob(
+    client: &Client,
+    base_url: &str,
+    name: &str,
+    credentials: &RegistryCredentials,
+    content: &BlobContent<'_>,
+    digest: &str,
+    progress: Progress,
+) -> Result<(), ZotError> {
+    let mut attempt = 0;
+    loop {
+        let result = async {
+            let upload_url = init_upload(client, base_url, name, credentials).await?;
+            upload_blob(
+                client,
+                &upload_url,
+                credentials,
+                content,
+                digest,
+                progress.clone(),
+            )
+            .await
+        }
+        .await;
+        let error = match result {
+            Err(error) if error.is_transient() => error,
+            result => return result,
+        };
+        let delay = match retry_delay(attempt) {
+            Some(delay) => delay,
+            None => return Err(error),
+        };
+        tracing::debug!(digest, attempt = attempt + 1, error = %error, "nouvel envoi du blob");
+        tokio::time::sleep(delay).await;
+        if let Ok(true) = blob_exists(client, base_url, name, digest, credentials).await {
+            return Ok(());
+        }
+        attempt += 1;
+    }
+}
+
+pub fn manifest_url(base_url: &str, name: &str, reference: &str) -> String {
+    format!("{}/v2/{}/manifests/{}", base_url, name, reference)
+}
+
+// Corps brut du manifest (ou de l'index) ; None si la référence n'existe pas
+pub async fn fetch_manifest_raw(
+    client: &Client,
+    base_url: &str,
+    name: &str,
+    reference: &str,
+    credentials: &RegistryCredentials,
+) -> Result<Option<Vec<u8>>, ZotError> {
+    let response = send(
+        "get_manifest",
+        credentials
+            .apply(client.get(manifest_url(base_url, name, reference)))
+            .header(
+                "Accept",
+                format!("{}, {}", MANIFEST_MEDIA_TYPE, INDEX_MEDIA_TYPE),
+            ),
+    )
+    .await
+    .map_err(|e| ZotError::upstream("Erreur récupération manifest", e))?;
+
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(ZotError::status(
+            "Erreur statut manifest",
+            response.status(),
+        ));
+    }
+
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| ZotError::upstream("Erreur lecture manifest", UpstreamError::Transport(e)))?;
+    METRICS.downloaded(bytes.len());
+    Ok(Some(bytes.to_vec()))
+}
+
+pub async fn fetch_manifest<T: DeserializeOwned>(
+    client: &Client,
+    base_url: &str,
+    name: &str,
+    reference: &str,
+    credentials: &RegistryCredentials,
+) -> Result<Option<(T, String)>, ZotError> {
+    let bytes = match fetch_manifest_raw(client, base_url, name, reference, credentials).await? {
+        Some(bytes) => bytes,
+        None => return Ok(None),
+    };
+    let manifest =
+        serde_json::from_slice(&bytes).map_err(|e| format!("Erreur parsing manifest: {}", e))?;
+    Ok(Some((manifest, calculate_sha256(&bytes))))
+}
+
+pub async fn fetch_blob(
+    client: &Client,
+    base_url: &str,
+    name: &str,
+    digest: &str,
+    credentials: &RegistryCredentials,
+) -> Result<Vec<u8>, ZotError> {
+    let blob_url = format!("{}/v2/{}/blobs/{}", base_url, name, digest);
+    let response = send("get_blob", credentials.apply(client.get(&blob_url)))
+        .await
+        .map_err(|e| ZotError::upstream("Erreur requête blob", e))?;
+
+    if !response.status().is_success() {
+        return Err(ZotError::status(
+            "Erreur récupération blob",
+            response.status(),
+        ));
+    }
+
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| ZotError::upstream("Erreur lecture blob", UpstreamError::Transport(e)))?;
+    METRICS.downloaded(bytes.len());
+    if calculate_sha256(&bytes) != digest {
+        return Err(format!("Digest du blob {} ne correspond pas", digest).into());
+    }
+    Ok(bytes.to_vec())
+}
+
+pub async fn blob_exists(
+    client: &Client,
+    base_url: &str,
+    name: &str,
+    digest: &str,
+    credentials: &RegistryCredentials,
+) -> Result<bool, ZotError> {
+    let blob_url = format!("{}/v2/{}/blobs/{}", base_url, name, digest);
+    let response = send("head_blob", credentials.apply(client.head(&blob_url)))
+        .await
+        .map_err(|e| ZotError::upstream("Erreur requête blob", e))?;
+
+    match response.status() {
+        status if status.is_success() => Ok(true),
+        StatusCode::NOT_FOUND => Ok(false),
+        status => Err(ZotError::status("Erreur statut blob", status)),
+    }
+}
+
+pub async fn put_manifest(
+    client: &Client,
+    manifest_url: &str,
+    credentials: &RegistryCredentials,
+    media_type: &str,
+    body: Vec<u8>,
+) -> Result<(), ZotError> {
+    let size = body.len();
+    let response = send(
+        "put_manifest",
+        credentials
+            .apply(client.put(manifest_url))
+            .header("Content-Type", media_type)
+            .body(body),
+    )
+    .await
+    .map_err(|e| ZotError::upstream("Erreur", e))?;
+
+    if !response.status().is_success() {
+        return Err(ZotError::status("Erreur manifest", response.status()));
+    }
+    METRICS.uploaded(size);
+    Ok(())
+}
+
+// Point d'entrée de l'API OCI : 200 si Zot répond et accepte les identifiants
+pub async fn ping_registry(
+    client: &Client,
+    base_url: &str,
+    credentials: &RegistryCredentials,
+    timeout: std::time::Duration,
+) -> Result<StatusCode, UpstreamError> {
+    let url = format!("{}/v2/", base_url);
+    let response = send_once("ping", credentials.apply(client.get(&url)).timeout(timeout)).await?;
+    Ok(response.status())
+}
+
+pub async fn list_repositories(
+    client: &Client,
+    base_url: &str,
+    credentials: &RegistryCredentials,
+) -> Result<Vec<String>, ZotError> {
+    let catalog_url = format!("{}/v2/_catalog", base_url);
+    let response = send(
+        "list_repositories",
+        credentials.apply(client.get(&catalog_url)),
+    )
+    .await
+    .map_err(|e| ZotError::upstream("Erreur récupération catalogue", e))?;
+
+    if !response.status().is_success() {
+        return Err(ZotError::status(
+            "Erreur statut catalogue",
+            response.status(),
+        ));
+    }
+
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| ZotError::upstream("Erreur parsing catalogue", UpstreamError::Transport(e)))?;
+    Ok(body
+        .get("repositories")
+        .and_then(|repositories| repositories.as_array())
+        .map(|repositories| {
+            repositories
+                .iter()
+                .filter_map(|repository| repository.as_str().map(|r| r.to_string()))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+pub async fn list_tags(
+    client: &Client,
+    base_url: &str,
+    name: &str,
+    credentials: &RegistryCredentials,
+) -> Result<Vec<String>, ZotError> {
+    let tags_url = format!("{}/v2/{}/tags/list", base_url, name);
+    let response = send("list_tags", credentials.apply(client.get(&tags_url)))
+        .await
+        .map_err(|e| ZotError::upstream("Erreur récupération tags", e))?;
+
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(Vec::new());
+    }
+    if !response.status().is_success() {
+        return Err(ZotError::status("Erreur statut tags", response.status()));
+    }
+
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| ZotError::upstream("Erreur parsing tags", UpstreamError::Transport(e)))?;
+    Ok(body
+        .get("tags")
+        .and_then(|tags| tags.as_array())
+        .map(|tags| {
+            tags.iter()
+                .filter_map(|tag| tag.as_str().map(|tag| tag.to_string()))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+// Retourne false si la référence n'existait pas
+pub async fn delete_manifest(
+    client: &Client,
+    base_url: &str,
+    name: &str,
+    reference: &str,
+    credentials: &RegistryCredentials,
+) -> Result<bool, ZotError> {
+    let response = send(
+        "delete_manifest",
+        credentials.apply(client.delete(manifest_url(base_url, name, reference))),
+    )
+    .await
+    .map_err(|e| ZotError::upstream("Erreur suppression manifest", e))?;
+
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(false),
+        status if status.is_success() => Ok(true),
+        status => Err(ZotError::status(
+            &format!("Erreur suppression manifest {}", reference),
+            status,
+        )),
+    }
+}
+
+pub async fn delete_blob(
+    client: &Client,
+    base_url: &str,
+    name: &str,
+    digest: &str,
+    credentials: &RegistryCredentials,
+) -> Result<bool, ZotError> {
+    let blob_url = format!("{}/v2/{}/blobs/{}", base_url, name, digest);
+    let response = send("delete_blob", credentials.apply(client.delete(&blob_url)))
+        .await
+        .map_err(|e| ZotError::upstream("Erreur suppression blob", e))?;
+
+    match response.status() {
+        StatusCode::NOT_FOUND => Ok(false),
+        status if status.is_success() => Ok(true),
+        status => Err(ZotError::status(
+            &format!("Erreur suppression blob {}", digest),
+            status,
+        )),
+    }
+}
+
+// Motif glob minimal : `*` pour toute suite de caractères, `?` pour un caractère
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+// JSON Merge Patch (RFC 7386)
+pub fn apply_merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(serde_json::Map::new());
+    }
+    let target = target.as_object_mut().unwrap();
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            apply_merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+// Fichier JSON d'un état persisté. L'écriture passe par un fichier temporaire renommé ensuite :
+// un arrêt brutal laisse l'ancienne version intacte. Un fichier illisible est une erreur, pas
+// un état vide, pour ne rien perdre silencieusement
+pub struct JsonFile {
+    path: PathBuf,
+}
+
+impl JsonFile {
+    pub fn new(path: PathBuf) -> Self {
+        JsonFile { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Fichier absent : état par défaut (premier démarrage)
+    pub fn load<T: DeserializeOwned + Default>(&self) -> Result<T, String> {
+        match fs::read(&self.path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|e| format!("Fichier {} illisible: {}", self.path.display(), e)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(T::default()),
+            Err(e) => Err(format!("Erreur lecture {}: {}", self.path.display(), e)),
+        }
+    }
+
+    pub fn save<T: Serialize + ?Sized>(&self, value: &T) -> Result<(), String> {
+        let content = serde_json::to_vec(value).map_err(|e| e.to_string())?;
+        let directory = match self.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let mut file = tempfile::NamedTempFile::new_in(directory).map_err(|e| e.to_string())?;
+        file.write_all(&content).map_err(|e| e.to_string())?;
+        file.as_file().sync_all().map_err(|e| e.to_string())?;
+        file.persist(&self.path).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{HeaderName, HeaderValue};
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::Instrument;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+use crate::config::{LogFormat, TelemetryConfig};
+use crate::services::calculate_sha256;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-ID";
+
+tokio::task_local! {
+    // Identifiant de la requête en cours, repris par les appels à Zot
+    static REQUEST_ID: String;
+}
+
+pub fn init(config: &TelemetryConfig) {
+    // RUST_LOG prime sur le niveau configuré
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.level));
+    let output = match config.format {
+        LogFormat::Json => tracing_subscriber::fmt::layer().json().boxed(),
+        LogFormat::Pretty => tracing_subscriber::fmt::layer().boxed(),
+    };
+
+    tracing_subscriber::registry()
+        .with(otlp_layer(config))
+        .with(output)
+        .with(filter)
+        .init();
+
+    if cfg!(not(feature = "otlp")) && config.otlp_endpoint.is_some() {
+        tracing::warn!("otlp_endpoint ignoré : service compilé sans la feature `otlp`");
+    }
+}
+
+type RegistryLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+#[cfg(feature = "otlp")]
+fn otlp_layer(config: &TelemetryConfig) -> Option<RegistryLayer> {
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::{runtime, trace::TracerProvider, Resource};
+
+    let endpoint = config.otlp_endpoint.as_ref()?;
+    let exporter = match SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint)
+        .build()
+    {
+        Ok(exporter) => exporter,
+        Err(e) => {
+            eprintln!("Export OTLP désactivé: {}", e);
+            return None;
+        }
+    };
+    let provider = TracerProvider::builder()
+        .with_batch_exporter(exporter, runtime::Tokio)
+        .with_resource(Resource::new(vec![KeyValue::new(
+            "service.name",
+            config.service_name.clone(),
+        )]))
+        .build();
+    let tracer = provider.tracer("poc");
+    opentelemetry::global::set_tracer_provider(provider);
+    Some(tracing_opentelemetry::layer().with_tracer(tracer).boxed())
+}
+
+#[cfg(not(feature = "otlp"))]
+fn otlp_layer(_config: &TelemetryConfig) -> Option<RegistryLayer> {
+    None
+}
+
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+static REQUEST_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+fn generate_request_id() -> String {
+    let seed = format!(
+        "{:?}-{}",
+        std::time::SystemTime::now(),
+        REQUEST_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+    );
+    calculate_sha256(seed.as_bytes())["sha256:".len()..][..32].to_string()
+}
+
+// Span par requête entrante ; l'identifiant est repris de l'en-tête X-Request-ID ou généré,
+// puis renvoyé dans la réponse
+pub async fn trace_request<B>(
+    request_id: Option<String>,
+    method: String,
+    route: String,
+    response: impl Future<Output = Result<ServiceResponse<B>, actix_web::Error>>,
+) -> Result<ServiceResponse<B>, actix_web::Error> {
+    let request_id = request_id
+        .filter(|id| !id.is_empty() && id.len() <= 128)
+        .unwrap_or_else(generate_request_id);
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %method,
+        route = %route,
+        status = tracing::field::Empty,
+    );
+
+    let started = std::time::Instant::now();
+    let response = REQUEST_ID
+        .scope(request_id.clone(), response)
+        .instrument(span.clone())
+        .await;
+
+    let status = match &response {
+        Ok(response) => response.status(),
+        Err(e) => e.as_response_error().status_code(),
+    };
+    span.record("status", status.as_u16());
+    span.in_scope(|| {
+        tracing::info!(
+            duration_ms = started.elapsed().as_millis() as u64,
+            "requête traitée"
+        )
+    });
+
+    response.map(|mut response| {
+        if let Ok(value) = HeaderValue::from_str(&request_id) {
+            response
+                .headers_mut()
+                .insert(HeaderName::from_static("x-request-id"), value);
+        }
+        response
+    })
+}
@@ -0,0 +1,102 @@
+use rustls::crypto::ring::{default_provider, sign::any_supported_type};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::SystemTime;
+
+use crate::config::TlsConfig;
+
+fn load_certified_key(config: &TlsConfig) -> Result<CertifiedKey, String> {
+    let cert_file = fs::File::open(&config.cert_path)
+        .map_err(|e| format!("Erreur lecture certificat {}: {}", config.cert_path, e))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(cert_file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Certificat invalide {}: {}", config.cert_path, e))?;
+    if certs.is_empty() {
+        return Err(format!("Aucun certificat dans {}", config.cert_path));
+    }
+
+    let key_file = fs::File::open(&config.key_path)
+        .map_err(|e| format!("Erreur lecture clé {}: {}", config.key_path, e))?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(key_file))
+        .map_err(|e| format!("Clé invalide {}: {}", config.key_path, e))?
+        .ok_or_else(|| format!("Aucune clé privée dans {}", config.key_path))?;
+    let signing_key = any_supported_type(&key)
+        .map_err(|e| format!("Clé non supportée {}: {}", config.key_path, e))?;
+
+    // Évite de servir une paire incohérente, par exemple pendant le remplacement des fichiers
+    let certified_key = CertifiedKey::new(certs, signing_key);
+    match certified_key.keys_match() {
+        Err(rustls::Error::InconsistentKeys(rustls::InconsistentKeys::KeyMismatch)) => {
+            Err(format!(
+                "Certificat et clé ne correspondent pas: {}",
+                config.cert_path
+            ))
+        }
+        _ => Ok(certified_key),
+    }
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(Path::new(path))
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+// Certificat servi aux nouvelles connexions, remplacé à chaud quand les fichiers changent
+#[derive(Debug)]
+pub struct ReloadingCertificate {
+    config: TlsConfig,
+    current: RwLock<Arc<CertifiedKey>>,
+    loaded_from: Mutex<(Option<SystemTime>, Option<SystemTime>)>,
+}
+
+impl ReloadingCertificate {
+    pub fn load(config: &TlsConfig) -> Result<Self, String> {
+        let loaded_from = (modified(&config.cert_path), modified(&config.key_path));
+        Ok(ReloadingCertificate {
+            config: config.clone(),
+            current: RwLock::new(Arc::new(load_certified_key(config)?)),
+            loaded_from: Mutex::new(loaded_from),
+        })
+    }
+
+    // Recharge si l'un des fichiers a changé ; en cas d'erreur (fichiers en cours d'écriture,
+    // paire incohérente) l'ancien certificat reste servi
+    pub fn reload_if_changed(&self) -> Result<bool, String> {
+        let observed = (
+            modified(&self.config.cert_path),
+            modified(&self.config.key_path),
+        );
+        let mut loaded_from = self.loaded_from.lock().unwrap();
+        if *loaded_from == observed {
+            return Ok(false);
+        }
+
+        let certified_key = load_certified_key(&self.config)?;
+        *self.current.write().unwrap() = Arc::new(certified_key);
+        *loaded_from = observed;
+        Ok(true)
+    }
+}
+
+impl ResolvesServerCert for ReloadingCertificate {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current.read().unwrap().clone())
+    }
+}
+
+pub fn server_config(
+    certificate: Arc<ReloadingCertificate>,
+) -> Result<rustls::ServerConfig, String> {
+    Ok(
+        rustls::ServerConfig::builder_with_provider(Arc::new(default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|e| format!("Configuration TLS invalide: {}", e))?
+            .with_no_client_auth()
+            .with_cert_resolver(certificate),
+    )
+}
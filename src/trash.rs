@@ -0,0 +1,270 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashSet;
+
+use crate::audit::{audited_task, AuditAction};
+use crate::deletion::{delete_tags, is_internal_tag, DeleteReport};
+use crate::entities::{AppState, Descriptor, ImageIndex, Operation, RegistryCredentials};
+use crate::history::{descriptor_of, history_tag, tag_manifest};
+use crate::manifest_builder::INDEX_MEDIA_TYPE;
+use crate::resilience::ZotError;
+use crate::services::{
+    delete_manifest, fetch_manifest, fetch_manifest_raw, list_repositories, list_tags,
+    manifest_url, put_manifest,
+};
+
+// Une version supprimée est retaguée `_trash.{reference}` : un index OCI qui garde le manifest
+// référencé et porte la date de suppression, jusqu'à la purge ou la restauration
+pub const TRASH_TAG_PREFIX: &str = "_trash.";
+const DELETED_ANNOTATION: &str = "com.aneocorp.trash.deleted";
+const REFERENCE_ANNOTATION: &str = "com.aneocorp.trash.reference";
+// Acteur consigné dans le journal d'audit pour les versions purgées
+const PURGE_ACTOR: &str = "trash-purge";
+
+pub fn trash_tag(reference: &str) -> String {
+    format!("{}{}", TRASH_TAG_PREFIX, reference)
+}
+
+#[derive(Serialize)]
+pub struct TrashEntry {
+    pub reference: String,
+    pub digest: String,
+    pub deleted_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+pub enum TrashError {
+    NotFound,
+    // La version a été republiée depuis sa suppression
+    Conflict,
+    // Une autre version de même référence attend déjà en corbeille
+    AlreadyTrashed,
+    Expired,
+    Registry(ZotError),
+}
+
+impl From<ZotError> for TrashError {
+    fn from(e: ZotError) -> Self {
+        TrashError::Registry(e)
+    }
+}
+
+fn trash_entry(state: &AppState, index: &ImageIndex) -> Option<TrashEntry> {
+    let annotations = index.annotations.as_ref()?;
+    let reference = annotations.get(REFERENCE_ANNOTATION)?.as_str()?;
+    let deleted_at = annotations.get(DELETED_ANNOTATION)?.as_str()?;
+    let deleted_at = DateTime::parse_from_rfc3339(deleted_at)
+        .ok()?
+        .with_timezone(&Utc);
+
+    Some(TrashEntry {
+        reference: reference.to_string(),
+        digest: index.manifests.first()?.digest.clone(),
+        deleted_at,
+        expires_at: deleted_at + Duration::hours(state.trash_config.retention_hours),
+    })
+}
+
+pub async fn trash_version(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    reference: &str,
+) -> Result<TrashEntry, TrashError> {
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+
+    let raw = fetch_manifest_raw(&client, &zot.url, repository, reference, credentials)
+        .await?
+        .ok_or(TrashError::NotFound)?;
+    let descriptor = descriptor_of(&raw);
+
+    // Écraser l'entrée existante rendrait la version précédente irrécupérable ; seule une entrée
+    // expirée, ou portant le même manifest, est remplacée
+    if let Some((entry, _)) = load_trash_entry(state, credentials, repository, reference).await? {
+        if entry.digest != descriptor.digest && entry.expires_at > Utc::now() {
+            return Err(TrashError::AlreadyTrashed);
+        }
+    }
+
+    let mut annotations = serde_json::Map::new();
+    annotations.insert(
+        DELETED_ANNOTATION.to_string(),
+        Value::String(Utc::now().to_rfc3339()),
+    );
+    annotations.insert(
+        REFERENCE_ANNOTATION.to_string(),
+        Value::String(reference.to_string()),
+    );
+    let index = ImageIndex {
+        schema_version: 2,
+        media_type: INDEX_MEDIA_TYPE.to_string(),
+        manifests: vec![descriptor],
+        annotations: Some(annotations),
+    };
+    let body = serde_json::to_vec(&index)
+        .map_err(|e| ZotError::from(format!("Erreur sérialisation: {}", e)))?;
+
+    put_manifest(
+        &client,
+        &manifest_url(&zot.url, repository, &trash_tag(reference)),
+        credentials,
+        INDEX_MEDIA_TYPE,
+        body,
+    )
+    .await?;
+
+    // Suppression par tag : seul le tag disparaît, le manifest reste référencé par l'index
+    delete_manifest(&client, &zot.url, repository, reference, credentials).await?;
+
+    Ok(trash_entry(state, &index).expect("entrée construite ci-dessus"))
+}
+
+pub async fn trash_all_versions(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+) -> Result<Vec<TrashEntry>, TrashError> {
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+
+    let versions: Vec<String> = list_tags(&client, &zot.url, repository, credentials)
+        .await?
+        .into_iter()
+        .filter(|tag| !is_internal_tag(tag))
+        .collect();
+    if versions.is_empty() {
+        return Err(TrashError::NotFound);
+    }
+
+    let mut entries = Vec::new();
+    for version in versions {
+        entries.push(trash_version(state, credentials, repository, &version).await?);
+    }
+    Ok(entries)
+}
+
+async fn load_trash_entry(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    reference: &str,
+) -> Result<Option<(TrashEntry, String)>, ZotError> {
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+
+    let index = fetch_manifest::<ImageIndex>(
+        &client,
+        &zot.url,
+        repository,
+        &trash_tag(reference),
+        credentials,
+    )
+    .await?;
+    Ok(index.and_then(|(index, digest)| Some((trash_entry(state, &index)?, digest))))
+}
+
+pub async fn list_trash(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+) -> Result<Vec<TrashEntry>, ZotError> {
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+
+    let mut entries = Vec::new();
+    for tag in list_tags(&client, &zot.url, repository, credentials).await? {
+        if let Some(reference) = tag.strip_prefix(TRASH_TAG_PREFIX) {
+            if let Some((entry, _)) =
+                load_trash_entry(state, credentials, repository, reference).await?
+            {
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+pub async fn restore_version(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+    repository: &str,
+    reference: &str,
+) -> Result<Descriptor, TrashError> {
+    let (entry, index_digest) = load_trash_entry(state, credentials, repository, reference)
+        .await?
+        .ok_or(TrashError::NotFound)?;
+    if entry.expires_at < Utc::now() {
+        return Err(TrashError::Expired);
+    }
+
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+
+    let current = fetch_manifest_raw(&client, &zot.url, repository, reference, credentials).await?;
+    if current.is_some() {
+        return Err(TrashError::Conflict);
+    }
+
+    let raw = fetch_manifest_raw(&client, &zot.url, repository, &entry.digest, credentials)
+        .await?
+        .ok_or_else(|| ZotError::from(format!("Manifest {} absent du registre", entry.digest)))?;
+
+    let media_type = descriptor_of(&raw).media_type;
+    let descriptor = tag_manifest(
+        state,
+        credentials,
+        repository,
+        reference,
+        &media_type,
+        raw,
+        Operation::Restore,
+    )
+    .await?;
+
+    delete_manifest(&client, &zot.url, repository, &index_digest, credentials).await?;
+
+    Ok(descriptor)
+}
+
+// Supprime définitivement les versions dont la rétention en corbeille est dépassée ; chaque
+// version purgée est consignée et notifiée comme une suppression
+pub async fn purge_expired(
+    state: &AppState,
+    credentials: &RegistryCredentials,
+) -> Result<Vec<DeleteReport>, ZotError> {
+    let client = state.registry_client.lock().unwrap().clone();
+    let zot = &state.zot_config;
+    let now = Utc::now();
+
+    let mut reports = Vec::new();
+    for repository in list_repositories(&client, &zot.url, credentials).await? {
+        let tags = list_tags(&client, &zot.url, &repository, credentials).await?;
+
+        for entry in list_trash(state, credentials, &repository).await? {
+            if entry.expires_at >= now {
+                continue;
+            }
+            let mut expired = HashSet::from([trash_tag(&entry.reference)]);
+            // L'historique reste attaché à la version si elle a été republiée entre-temps
+            if !tags.contains(&entry.reference) {
+                expired.insert(history_tag(&entry.reference));
+            }
+
+            let report = audited_task(
+                state,
+                PURGE_ACTOR,
+                credentials,
+                AuditAction::Delete,
+                &repository,
+                &entry.reference,
+                &entry.digest,
+                delete_tags(state, credentials, &repository, &expired),
+            )
+            .await?;
+            reports.push(report);
+        }
+    }
+    Ok(reports)
+}
@@ -0,0 +1,528 @@
+use actix_multipart::{Field, Multipart};
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::{web, HttpRequest, HttpResponse};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures::{Stream, StreamExt};
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+use crate::config::UploadConfig;
+use crate::entities::{AppState, Layer, ManifestMetadata};
+use crate::manifest_builder::{config_content, wasm_layer_of};
+use crate::services::calculate_sha256;
+
+// CRD encodé en base64, pour un PUT `application/wasm`
+pub const MANIFEST_HEADER: &str = "X-Component-Manifest";
+
+// Fichier joint au composant, publié comme couche supplémentaire après le binaire
+pub struct Attachment {
+    pub name: String,
+    pub media_type: String,
+    pub content: Vec<u8>,
+}
+
+// Binaire écrit dans un fichier temporaire à mesure de sa réception : seule sa couche (digest
+// et taille) reste en mémoire, et la publication relit le fichier
+pub struct SpooledWasm {
+    pub layer: Layer,
+    pub file: NamedTempFile,
+}
+
+// Écriture du binaire dans son fichier temporaire, digest calculé au fil des morceaux
+struct Spool {
+    file: NamedTempFile,
+    hasher: Sha256,
+    size: u64,
+}
+
+impl Spool {
+    // Nom unique et droits restreints au propriétaire ; le nom fourni par le client n'est pas
+    // utilisé
+    fn create(config: &UploadConfig) -> Result<Self, HttpResponse> {
+        let mut builder = tempfile::Builder::new();
+        builder.prefix("poc-upload-").suffix(".wasm");
+        let file = match &config.temp_dir {
+            Some(dir) => builder.tempfile_in(dir),
+            None => builder.tempfile(),
+        }
+        .map_err(spool_error)?;
+        Ok(Spool {
+            file,
+            hasher: Sha256::new(),
+            size: 0,
+        })
+    }
+
+    fn write(&mut self, chunk: &[u8]) -> Result<(), HttpResponse> {
+        self.file.write_all(chunk).map_err(spool_error)?;
+        self.hasher.update(chunk);
+        self.size += chunk.len() as u64;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<SpooledWasm, HttpResponse> {
+        self.file.flush().map_err(spool_error)?;
+        let digest = format!("sha256:{}", hex::encode(self.hasher.finalize()));
+        Ok(SpooledWasm {
+            layer: wasm_layer_of(digest, self.size),
+            file: self.file,
+        })
+    }
+}
+
+fn spool_error(e: std::io::Error) -> HttpResponse {
+    HttpResponse::InternalServerError().body(format!("Erreur fichier temporaire: {}", e))
+}
+
+// Binaire déjà en mémoire (base64 d'un document JSON)
+fn spool(config: &UploadConfig, content: &[u8]) -> Result<SpooledWasm, HttpResponse> {
+    let mut spool = Spool::create(config)?;
+    spool.write(content)?;
+    spool.finish()
+}
+
+// Le fichier temporaire est supprimé à la destruction de l'upload, quel que soit le chemin de sortie
+#[derive(Default)]
+pub struct ComponentUpload {
+    pub manifest: Option<ManifestMetadata>,
+    pub wasm: Option<SpooledWasm>,
+    pub attachments: Vec<Attachment>,
+}
+
+impl ComponentUpload {
+    // Empreinte du contenu décodé : deux envois identiques encodés différemment (frontière
+    // multipart, base64, binaire brut) ont la même empreinte
+    pub fn fingerprint(&self) -> String {
+        let mut parts = vec![
+            self.manifest
+                .as_ref()
+                .map(|manifest| calculate_sha256(&config_content(manifest)))
+                .unwrap_or_default(),
+            self.wasm
+                .as_ref()
+                .map(|wasm| wasm.layer.digest.clone())
+                .unwrap_or_default(),
+        ];
+        parts.extend(self.attachments.iter().map(|attachment| {
+            format!(
+                "{} {} {}",
+                attachment.name,
+                attachment.media_type,
+                calculate_sha256(&attachment.content)
+            )
+        }));
+        calculate_sha256(parts.join("\n").as_bytes())
+    }
+
+    fn set_manifest(&mut self, content: &[u8]) -> Result<(), HttpResponse> {
+        if self.manifest.is_some() {
+            return Err(HttpResponse::BadRequest().body("Partie en double: manifest"));
+        }
+        self.manifest = Some(
+            serde_json::from_slice(content)
+                .map_err(|e| HttpResponse::BadRequest().body(format!("Erreur manifest: {}", e)))?,
+        );
+        Ok(())
+    }
+
+    // Vérifié avant la lecture de la partie, pour ne pas la recevoir inutilement
+    fn check_no_wasm(&self) -> Result<(), HttpResponse> {
+        if self.wasm.is_some() {
+            return Err(HttpResponse::BadRequest().body("Partie en double: wasm"));
+        }
+        Ok(())
+    }
+
+    fn add_attachment(&mut self, attachment: Attachment) -> Result<(), HttpResponse> {
+        if self
+            .attachments
+            .iter()
+            .any(|existing| existing.name == attachment.name)
+        {
+            return Err(HttpResponse::BadRequest()
+                .body(format!("Pièce jointe en double: {}", attachment.name)));
+        }
+        self.attachments.push(attachment);
+        Ok(())
+    }
+}
+
+fn too_large(limit: u64) -> HttpResponse {
+    HttpResponse::PayloadTooLarge().body(format!("Taille maximale dépassée: {} octets", limit))
+}
+
+fn bad_request(message: String) -> HttpResponse {
+    HttpResponse::BadRequest().body(message)
+}
+
+// Lit le composant selon le Content-Type : formulaire multipart, document JSON
+// (binaire en base64 ou par URL) ou binaire brut `application/wasm`
+pub async fn read_component_upload(
+    req: &HttpRequest,
+    payload: web::Payload,
+    state: &AppState,
+) -> Result<ComponentUpload, HttpResponse> {
+    let content_type = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let essence = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+
+    match essence.as_str() {
+        "multipart/form-data" => {
+            read_multipart(Multipart::new(req.headers(), payload), &state.upload_config).await
+        }
+        "application/json" => read_json(payload, state).await,
+        "application/wasm" => read_raw_wasm(req, payload, state).await,
+        _ => Err(HttpResponse::UnsupportedMediaType().body(
+            "Content-Type attendu: multipart/form-data, application/json ou application/wasm",
+        )),
+    }
+}
+
+enum PartKind {
+    Manifest,
+    Wasm,
+    Attachment,
+}
+
+// Champs nommés `manifest`, `wasm` et `attachment` ; à défaut, l'extension du fichier
+// (.json ou .wasm) désigne la partie
+fn classify(field: &Field) -> Option<PartKind> {
+    match field.name() {
+        "manifest" => Some(PartKind::Manifest),
+        "wasm" => Some(PartKind::Wasm),
+        "attachment" => Some(PartKind::Attachment),
+        _ => match field.content_disposition().get_filename() {
+            Some(name) if name.ends_with(".json") => Some(PartKind::Manifest),
+            Some(name) if name.ends_with(".wasm") => Some(PartKind::Wasm),
+            _ => None,
+        },
+    }
+}
+
+async fn read_multipart(
+    mut payload: Multipart,
+    config: &UploadConfig,
+) -> Result<ComponentUpload, HttpResponse> {
+    let mut upload = ComponentUpload::default();
+    let mut total: u64 = 0;
+
+    while let Some(item) = payload.next().await {
+        let mut field = item.map_err(|e| bad_request(format!("Erreur multipart: {}", e)))?;
+
+        let kind = match classify(&field) {
+            Some(kind) => kind,
+            None => {
+                let part = field
+                    .content_disposition()
+                    .get_filename()
+                    .unwrap_or(field.name())
+                    .to_string();
+                return Err(bad_request(format!("Partie inattendue: {}", part)));
+            }
+        };
+
+        match kind {
+            PartKind::Wasm => {
+                upload.check_no_wasm()?;
+                let mut spool = Spool::create(config)?;
+                read_part(&mut field, config, &mut total, |chunk| spool.write(chunk)).await?;
+                upload.wasm = Some(spool.finish()?);
+            }
+            PartKind::Manifest => {
+                let content = read_part_content(&mut field, config, &mut total).await?;
+                upload.set_manifest(&content)?;
+            }
+            PartKind::Attachment => {
+                let name = match field.content_disposition().get_filename() {
+                    Some(name) => name.to_string(),
+                    None => {
+                        return Err(bad_request(
+                            "Nom de fichier requis pour une pièce jointe".to_string(),
+                        ))
+                    }
+                };
+                let media_type = field
+                    .content_type()
+                    .map(|mime| mime.essence_str().to_string())
+                    .unwrap_or_else(|| "application/octet-stream".to_string());
+                let content = read_part_content(&mut field, config, &mut total).await?;
+                upload.add_attachment(Attachment {
+                    name,
+                    media_type,
+                    content,
+                })?;
+            }
+        }
+    }
+
+    Ok(upload)
+}
+
+// Transmet la partie morceau par morceau à `sink`, dans les limites de taille par partie et
+// pour l'ensemble de la requête (`total`)
+async fn read_part(
+    field: &mut Field,
+    config: &UploadConfig,
+    total: &mut u64,
+    mut sink: impl FnMut(&[u8]) -> Result<(), HttpResponse>,
+) -> Result<(), HttpResponse> {
+    let mut size: u64 = 0;
+    while let Some(chunk) = field.next().await {
+        let chunk = chunk.map_err(|e| bad_request(format!("Erreur multipart: {}", e)))?;
+        size += chunk.len() as u64;
+        *total += chunk.len() as u64;
+        if size > config.max_part_bytes {
+            return Err(too_large(config.max_part_bytes));
+        }
+        if *total > config.max_total_bytes {
+            return Err(too_large(config.max_total_bytes));
+        }
+        sink(&chunk)?;
+    }
+    Ok(())
+}
+
+async fn read_part_content(
+    field: &mut Field,
+    config: &UploadConfig,
+    total: &mut u64,
+) -> Result<Vec<u8>, HttpResponse> {
+    let mut content = Vec::new();
+    read_part(field, config, total, |chunk| {
+        content.extend_from_slice(chunk);
+        Ok(())
+    })
+    .await?;
+    Ok(content)
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonUpload {
+    manifest: Option<ManifestMetadata>,
+    // Binaire encodé en base64, ou URL d'où le télécharger ; l'un ou l'autre
+    wasm: Option<String>,
+    wasm_url: Option<String>,
+    #[serde(default)]
+    attachments: Vec<JsonAttachment>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonAttachment {
+    name: String,
+    media_type: Option<String>,
+    // Contenu encodé en base64
+    content: String,
+}
+
+fn decode_base64(field: &str, value: &str, limit: u64) -> Result<Vec<u8>, HttpResponse> {
+    let content = BASE64
+        .decode(value.trim())
+        .map_err(|e| bad_request(format!("Base64 invalide ({}): {}", field, e)))?;
+    if content.len() as u64 > limit {
+        return Err(too_large(limit));
+    }
+    Ok(content)
+}
+
+async fn read_json(
+    payload: web::Payload,
+    state: &AppState,
+) -> Result<ComponentUpload, HttpResponse> {
+    let config = &state.upload_config;
+    let body = read_body(payload, config.max_total_bytes).await?;
+    let document: JsonUpload = serde_json::from_slice(&body)
+        .map_err(|e| bad_request(format!("Document JSON invalide: {}", e)))?;
+
+    let mut upload = ComponentUpload {
+        manifest: document.manifest,
+        ..ComponentUpload::default()
+    };
+    let wasm = match (document.wasm, document.wasm_url) {
+        (Some(_), Some(_)) => {
+            return Err(bad_request(
+                "wasm et wasm_url sont mutuellement exclusifs".to_string(),
+            ))
+        }
+        (Some(encoded), None) => Some(decode_base64("wasm", &encoded, config.max_part_bytes)?),
+        (None, Some(url)) => Some(fetch_remote(state, &url).await?),
+        (None, None) => None,
+    };
+    if let Some(wasm) = wasm {
+        upload.wasm = Some(spool(config, &wasm)?);
+    }
+    for attachment in document.attachments {
+        let content = decode_base64(&attachment.name, &attachment.content, config.max_part_bytes)?;
+        upload.add_attachment(Attachment {
+            name: attachment.name,
+            media_type: attachment
+                .media_type
+                .unwrap_or_else(|| "application/octet-stream".to_string()),
+            content,
+        })?;
+    }
+
+    Ok(upload)
+}
+
+#[derive(Deserialize)]
+struct RawUploadParams {
+    // URL du CRD, à défaut de l'en-tête X-Component-Manifest
+    manifest_url: Option<String>,
+}
+
+async fn read_raw_wasm(
+    req: &HttpRequest,
+    payload: web::Payload,
+    state: &AppState,
+) -> Result<ComponentUpload, HttpResponse> {
+    let config = &state.upload_config;
+    let params = web::Query::<RawUploadParams>::from_query(req.query_string())
+        .map_err(|e| bad_request(format!("Paramètres invalides: {}", e)))?;
+    let mut upload = ComponentUpload::default();
+
+    let header = req
+        .headers()
+        .get(MANIFEST_HEADER)
+        .map(|value| value.to_str().unwrap_or_default().to_string());
+    match (header, &params.manifest_url) {
+        (Some(_), Some(_)) => {
+            return Err(bad_request(format!(
+                "{} et manifest_url sont mutuellement exclusifs",
+                MANIFEST_HEADER
+            )))
+        }
+        (Some(encoded), None) => {
+            let content = decode_base64(MANIFEST_HEADER, &encoded, config.max_part_bytes)?;
+            upload.set_manifest(&content)?;
+        }
+        (None, Some(url)) => {
+            let content = fetch_remote(state, url).await?;
+            upload.set_manifest(&content)?;
+        }
+        // Mise à jour du seul binaire : le CRD courant est conservé
+        (None, None) => {}
+    }
+
+    let mut spool = Spool::create(config)?;
+    read_stream(payload, config.max_part_bytes, |chunk| spool.write(chunk)).await?;
+    upload.wasm = Some(spool.finish()?);
+    Ok(upload)
+}
+
+async fn read_body<S, E>(payload: S, limit: u64) -> Result<Vec<u8>, HttpResponse>
+where
+    S: Stream<Item = Result<web::Bytes, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    let mut body = Vec::new();
+    read_stream(payload, limit, |chunk| {
+        body.extend_from_slice(chunk);
+        Ok(())
+    })
+    .await?;
+    Ok(body)
+}
+
+// Transmet le corps morceau par morceau à `sink`, sans dépasser `limit` octets
+async fn read_stream<S, E>(
+    mut payload: S,
+    limit: u64,
+    mut sink: impl FnMut(&[u8]) -> Result<(), HttpResponse>,
+) -> Result<(), HttpResponse>
+where
+    S: Stream<Item = Result<web::Bytes, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    let mut size: u64 = 0;
+    while let Some(chunk) = payload.next().await {
+        let chunk = chunk.map_err(|e| bad_request(format!("Erreur lecture du corps: {}", e)))?;
+        size += chunk.len() as u64;
+        if size > limit {
+            return Err(too_large(limit));
+        }
+        sink(&chunk)?;
+    }
+    Ok(())
+}
+
+// L'URL et le préfixe sont comparés une fois analysés : même schéma, hôte et port, et chemin
+// normalisé (segments `..` et leurs encodages résolus) situé sous celui du préfixe. Un
+// séparateur encodé (`%2F`, `%5C`) est refusé : le serveur distant pourrait le décoder et
+// remonter hors du préfixe
+pub fn is_allowed(url: &Url, prefix: &str) -> bool {
+    let Ok(prefix) = Url::parse(prefix) else {
+        return false;
+    };
+    let base = prefix.path().trim_end_matches('/');
+    let path = url.path().to_ascii_lowercase();
+    !path.contains("%2f")
+        && !path.contains("%5c")
+        && url.scheme() == prefix.scheme()
+        && url.host_str() == prefix.host_str()
+        && url.port_or_known_default() == prefix.port_or_known_default()
+        && url.username().is_empty()
+        && url.password().is_none()
+        && (url.path() == base || url.path().starts_with(&format!("{}/", base)))
+}
+
+async fn fetch_remote(state: &AppState, url: &str) -> Result<Vec<u8>, HttpResponse> {
+    let config = &state.upload_config;
+    download(
+        &state.http_client,
+        &config.remote_url_prefixes,
+        url,
+        config.max_part_bytes,
+    )
+    .await
+}
+
+// Téléchargement limité aux préfixes autorisés, pour ne pas faire du service un relais
+// vers des adresses arbitraires ; le client HTTP ne suit pas les redirections
+pub async fn download(
+    client: &Client,
+    prefixes: &[String],
+    url: &str,
+    limit: u64,
+) -> Result<Vec<u8>, HttpResponse> {
+    let parsed =
+        Url::parse(url).map_err(|e| bad_request(format!("URL invalide {}: {}", url, e)))?;
+    if !prefixes.iter().any(|prefix| is_allowed(&parsed, prefix)) {
+        return Err(bad_request(format!("URL non autorisée: {}", url)));
+    }
+
+    let mut response = client.get(parsed).send().await.map_err(|e| {
+        HttpResponse::BadGateway().body(format!("Erreur téléchargement {}: {}", url, e))
+    })?;
+    if !response.status().is_success() {
+        return Err(HttpResponse::BadGateway().body(format!(
+            "Erreur téléchargement {}: {}",
+            url,
+            response.status()
+        )));
+    }
+
+    let mut content = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|e| {
+        HttpResponse::BadGateway().body(format!("Erreur téléchargement {}: {}", url, e))
+    })? {
+        if (content.len() + chunk.len()) as u64 > limit {
+            return Err(too_large(limit));
+        }
+        content.extend_from_slice(&chunk);
+    }
+    Ok(content)
+}
@@ -0,0 +1,271 @@
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::config::{WebhookConfig, WebhookEndpoint};
+use crate::entities::AppState;
+use crate::services::glob_match;
+use crate::store::JsonFile;
+
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+// `sha256=<hex>` : HMAC-SHA256 du corps de la requête avec la clé de l'endpoint
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum WebhookEvent {
+    #[serde(rename = "component.published")]
+    Published,
+    #[serde(rename = "component.updated")]
+    Updated,
+    #[serde(rename = "component.deleted")]
+    Deleted,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::Published => "component.published",
+            WebhookEvent::Updated => "component.updated",
+            WebhookEvent::Deleted => "component.deleted",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WebhookPayload {
+    pub event: WebhookEvent,
+    pub timestamp: DateTime<Utc>,
+    pub repository: String,
+    // Absente pour la suppression de toutes les versions d'un dépôt
+    pub version: Option<String>,
+    pub digest: Option<String>,
+    pub component_type: Option<String>,
+    pub actor: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Delivery {
+    pub id: String,
+    pub endpoint: String,
+    pub payload: WebhookPayload,
+    pub status: DeliveryStatus,
+    pub attempts: u32,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub last_attempt_at: Option<DateTime<Utc>>,
+    pub last_status: Option<u16>,
+    pub last_error: Option<String>,
+}
+
+// File des livraisons, persistée pour survivre aux redémarrages ; sert aussi de journal
+pub struct WebhookQueue {
+    file: JsonFile,
+    deliveries: Mutex<Vec<Delivery>>,
+    sequence: AtomicU64,
+}
+
+impl WebhookQueue {
+    pub fn open(path: PathBuf) -> Result<Self, String> {
+        let file = JsonFile::new(path);
+        let deliveries = file.load()?;
+        Ok(WebhookQueue {
+            file,
+            deliveries: Mutex::new(deliveries),
+            sequence: AtomicU64::new(0),
+        })
+    }
+
+    fn save(&self, deliveries: &[Delivery]) {
+        if let Err(e) = self.file.save(deliveries) {
+            tracing::error!(path = %self.file.path().display(), error = %e, "Erreur écriture file webhooks");
+        }
+    }
+
+    pub fn enqueue(&self, config: &WebhookConfig, payload: WebhookPayload) {
+        let endpoints: Vec<&WebhookEndpoint> = config
+            .endpoints
+            .iter()
+            .filter(|endpoint| {
+                (endpoint.events.is_empty() || endpoint.events.contains(&payload.event))
+                    && glob_match(&endpoint.repositories, &payload.repository)
+            })
+            .collect();
+        if endpoints.is_empty() {
+            return;
+        }
+
+        let now = Utc::now();
+        let mut deliveries = self.deliveries.lock().unwrap();
+        for endpoint in endpoints {
+            let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+            deliveries.push(Delivery {
+                id: format!("{}-{}", now.timestamp_micros(), sequence),
+                endpoint: endpoint.name.clone(),
+                payload: payload.clone(),
+                status: DeliveryStatus::Pending,
+                attempts: 0,
+                created_at: now,
+                next_attempt_at: Some(now),
+                last_attempt_at: None,
+                last_status: None,
+                last_error: None,
+            });
+        }
+
+        // Seules les livraisons terminées les plus anciennes sont oubliées
+        let finished = deliveries
+            .iter()
+            .filter(|delivery| delivery.status != DeliveryStatus::Pending)
+            .count();
+        let mut excess = finished.saturating_sub(config.keep_deliveries);
+        deliveries.retain(|delivery| {
+            if excess > 0 && delivery.status != DeliveryStatus::Pending {
+                excess -= 1;
+                return false;
+            }
+            true
+        });
+        self.save(&deliveries);
+    }
+
+    fn due(&self, now: DateTime<Utc>) -> Vec<Delivery> {
+        let deliveries = self.deliveries.lock().unwrap();
+        deliveries
+            .iter()
+            .filter(|delivery| {
+                delivery.status == DeliveryStatus::Pending
+                    && delivery.next_attempt_at.is_some_and(|next| next <= now)
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn finish_attempt(
+        &self,
+        config: &WebhookConfig,
+        id: &str,
+        status: Option<u16>,
+        error: Option<String>,
+    ) {
+        let now = Utc::now();
+        let mut deliveries = self.deliveries.lock().unwrap();
+        let Some(delivery) = deliveries.iter_mut().find(|delivery| delivery.id == id) else {
+            return;
+        };
+
+        delivery.attempts += 1;
+        delivery.last_attempt_at = Some(now);
+        delivery.last_status = status;
+        if error.is_none() {
+            delivery.status = DeliveryStatus::Delivered;
+            delivery.next_attempt_at = None;
+        } else if delivery.attempts >= config.max_attempts {
+            delivery.status = DeliveryStatus::Failed;
+            delivery.next_attempt_at = None;
+        } else {
+            delivery.next_attempt_at = Some(now + backoff(config, delivery.attempts));
+        }
+        delivery.last_error = error;
+        self.save(&deliveries);
+    }
+
+    // Livraisons les plus récentes d'abord
+    pub fn deliveries(
+        &self,
+        status: Option<DeliveryStatus>,
+        endpoint: Option<&str>,
+    ) -> Vec<Delivery> {
+        let deliveries = self.deliveries.lock().unwrap();
+        deliveries
+            .iter()
+            .rev()
+            .filter(|delivery| status.is_none_or(|status| delivery.status == status))
+            .filter(|delivery| endpoint.is_none_or(|endpoint| delivery.endpoint == endpoint))
+            .cloned()
+            .collect()
+    }
+}
+
+// Délai avant la tentative suivant la `attempts`-ième
+pub fn backoff(config: &WebhookConfig, attempts: u32) -> Duration {
+    let seconds = config
+        .initial_backoff_seconds
+        .saturating_mul(1u64 << attempts.saturating_sub(1).min(32))
+        .min(config.max_backoff_seconds);
+    Duration::seconds(seconds as i64)
+}
+
+pub fn signature(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepte toute taille de clé");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+async fn send(
+    client: &reqwest::Client,
+    config: &WebhookConfig,
+    endpoint: &WebhookEndpoint,
+    delivery: &Delivery,
+) -> (Option<u16>, Option<String>) {
+    let body = match serde_json::to_vec(&delivery.payload) {
+        Ok(body) => body,
+        Err(e) => return (None, Some(format!("Erreur sérialisation: {}", e))),
+    };
+
+    let mut request = client
+        .post(&endpoint.url)
+        .timeout(std::time::Duration::from_secs(config.timeout_seconds))
+        .header("Content-Type", "application/json")
+        .header(EVENT_HEADER, delivery.payload.event.as_str())
+        .header(DELIVERY_HEADER, &delivery.id);
+    if let Some(secret) = &endpoint.secret {
+        request = request.header(SIGNATURE_HEADER, signature(secret, &body));
+    }
+
+    match request.body(body).send().await {
+        Ok(response) if response.status().is_success() => (Some(response.status().as_u16()), None),
+        Ok(response) => (
+            Some(response.status().as_u16()),
+            Some(format!("Erreur statut: {}", response.status())),
+        ),
+        Err(e) => (None, Some(format!("Erreur envoi: {}", e))),
+    }
+}
+
+// Tente les livraisons arrivées à échéance ; retourne le nombre de tentatives
+pub async fn deliver_due(state: &AppState) -> usize {
+    let config = &state.webhook_config;
+    let queue = &state.webhook_queue;
+    let client = state.http_client.clone();
+
+    let due = queue.due(Utc::now());
+    for delivery in &due {
+        let (status, error) = match config
+            .endpoints
+            .iter()
+            .find(|endpoint| endpoint.name == delivery.endpoint)
+        {
+            Some(endpoint) => send(&client, config, endpoint, delivery).await,
+            None => (
+                None,
+                Some("Endpoint absent de la configuration".to_string()),
+            ),
+        };
+        queue.finish_attempt(config, &delivery.id, status, error);
+    }
+    due.len()
+}
@@ -0,0 +1,102 @@
+use actix_web::http::StatusCode;
+use poc::auth::{Caller, Grant, Role};
+use poc::entities::RegistryCredentials;
+use poc::services::glob_match;
+
+fn caller(grants: &[(Role, &str)]) -> Caller {
+    Caller {
+        name: "test".to_string(),
+        grants: grants
+            .iter()
+            .map(|(role, repositories)| Grant {
+                role: *role,
+                repositories: repositories.to_string(),
+            })
+            .collect(),
+        credentials: RegistryCredentials::Bearer("token".to_string()),
+    }
+}
+
+#[test]
+fn glob_match_wildcards() {
+    assert!(glob_match("*", ""));
+    assert!(glob_match("*", "team-a/filter"));
+    assert!(glob_match("team-a-*", "team-a-filter"));
+    assert!(glob_match("team-a-*", "team-a-"));
+    assert!(!glob_match("team-a-*", "team-b-filter"));
+    assert!(glob_match("*-filter", "team-a-filter"));
+    assert!(glob_match("team-?-filter", "team-a-filter"));
+    assert!(!glob_match("team-?-filter", "team-ab-filter"));
+    assert!(glob_match("a*b*c", "a-b-b-c"));
+    assert!(!glob_match("a*b*c", "a-b-b-d"));
+}
+
+#[test]
+fn glob_match_is_exact_without_wildcards() {
+    assert!(glob_match("http-filter", "http-filter"));
+    assert!(!glob_match("http-filter", "http-filter2"));
+    assert!(!glob_match("http-filter", "http-filte"));
+    assert!(!glob_match("", "http-filter"));
+}
+
+#[test]
+fn roles_include_lower_roles() {
+    assert!(Role::Reader < Role::Publisher);
+    assert!(Role::Publisher < Role::Admin);
+
+    let publisher = caller(&[(Role::Publisher, "*")]);
+    assert!(publisher.can(Role::Reader, "http-filter"));
+    assert!(publisher.can(Role::Publisher, "http-filter"));
+    assert!(!publisher.can(Role::Admin, "http-filter"));
+}
+
+#[test]
+fn grants_are_scoped_by_repository_pattern() {
+    let caller = caller(&[(Role::Publisher, "team-a-*"), (Role::Reader, "*")]);
+    assert!(caller.can(Role::Publisher, "team-a-filter"));
+    assert!(!caller.can(Role::Publisher, "team-b-filter"));
+    assert!(caller.can(Role::Reader, "team-b-filter"));
+    assert!(!caller.can(Role::Admin, "team-a-filter"));
+
+    assert!(caller.require(Role::Publisher, "team-a-filter").is_ok());
+    let denied = caller.require(Role::Publisher, "team-b-filter").unwrap_err();
+    assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn caller_without_grants_is_denied() {
+    let caller = caller(&[]);
+    assert!(!caller.can(Role::Reader, "http-filter"));
+    assert!(caller.require_global(Role::Reader).is_err());
+}
+
+#[test]
+fn global_operations_require_a_wildcard_grant() {
+    let scoped_admin = caller(&[(Role::Admin, "team-a-*")]);
+    assert!(scoped_admin.can(Role::Admin, "team-a-filter"));
+    let denied = scoped_admin.require_global(Role::Admin).unwrap_err();
+    assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+
+    // Un motif équivalent à `*` ne donne pas de droits globaux : seul `*` les accorde
+    let double_star = caller(&[(Role::Admin, "**")]);
+    assert!(double_star.require_global(Role::Admin).is_err());
+
+    let admin = caller(&[(Role::Admin, "*")]);
+    assert!(admin.require_global(Role::Admin).is_ok());
+    assert!(admin.require_global(Role::Reader).is_ok());
+
+    let reader = caller(&[(Role::Reader, "*")]);
+    assert!(reader.require_global(Role::Reader).is_ok());
+    assert!(reader.require_global(Role::Admin).is_err());
+}
+
+#[test]
+fn publishers_of_any_repository_may_start_an_upload() {
+    let scoped = caller(&[(Role::Publisher, "team-a-*")]);
+    assert!(scoped.require_any(Role::Publisher).is_ok());
+
+    let reader = caller(&[(Role::Reader, "*")]);
+    let denied = reader.require_any(Role::Publisher).unwrap_err();
+    assert_eq!(denied.status(), StatusCode::FORBIDDEN);
+    assert!(caller(&[]).require_any(Role::Reader).is_err());
+}
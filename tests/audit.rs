@@ -0,0 +1,99 @@
+use chrono::{DateTime, TimeZone, Utc};
+use poc::audit::{AuditAction, AuditEvent, AuditLog, AuditOutcome};
+use std::fs::OpenOptions;
+use std::io::Write;
+use tempfile::TempDir;
+
+fn at(day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 3, day, 12, 0, 0).unwrap()
+}
+
+fn event(day: u32, repository: &str, action: AuditAction) -> AuditEvent {
+    AuditEvent {
+        timestamp: at(day),
+        caller: "ci".to_string(),
+        action,
+        repository: repository.to_string(),
+        reference: Some("1.0.0".to_string()),
+        old_digest: None,
+        new_digest: Some(format!("sha256:{:064}", day)),
+        outcome: AuditOutcome::Success,
+        status: Some(201),
+    }
+}
+
+fn days(events: &[AuditEvent]) -> Vec<String> {
+    events
+        .iter()
+        .map(|event| format!("{} {}", event.timestamp.format("%d"), event.repository))
+        .collect()
+}
+
+fn recorded(directory: &TempDir) -> AuditLog {
+    let log = AuditLog::open(Some(directory.path().join("audit.jsonl"))).unwrap();
+    log.record(&event(1, "components/echo", AuditAction::Push));
+    log.record(&event(2, "components/filter", AuditAction::Push));
+    log.record(&event(3, "components/echo", AuditAction::Update));
+    log.record(&event(4, "components/echo", AuditAction::Delete));
+    log
+}
+
+#[test]
+fn query_filters_by_repository_and_period() {
+    let directory = TempDir::new().unwrap();
+    let log = recorded(&directory);
+
+    assert_eq!(log.query(None, None, None).unwrap().len(), 4);
+    assert_eq!(
+        days(&log.query(Some("components/echo"), None, None).unwrap()),
+        vec![
+            "01 components/echo",
+            "03 components/echo",
+            "04 components/echo"
+        ]
+    );
+    // Bornes incluses
+    assert_eq!(
+        days(&log.query(None, Some(at(2)), Some(at(3))).unwrap()),
+        vec!["02 components/filter", "03 components/echo"]
+    );
+    assert_eq!(
+        days(
+            &log.query(Some("components/echo"), Some(at(2)), None)
+                .unwrap()
+        ),
+        vec!["03 components/echo", "04 components/echo"]
+    );
+    assert!(log
+        .query(Some("components/unknown"), None, None)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn events_survive_a_restart_and_truncated_lines_are_skipped() {
+    let directory = TempDir::new().unwrap();
+    drop(recorded(&directory));
+
+    // Arrêt brutal au milieu d'une écriture
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(directory.path().join("audit.jsonl"))
+        .unwrap();
+    writeln!(file, "{{\"timestamp\":\"2025-03-05T12:00").unwrap();
+
+    let log = AuditLog::open(Some(directory.path().join("audit.jsonl"))).unwrap();
+    log.record(&event(6, "components/echo", AuditAction::Trash));
+
+    let events = log.query(None, None, None).unwrap();
+    assert_eq!(events.len(), 5);
+    assert_eq!(events[4].action, AuditAction::Trash);
+    assert_eq!(events[2].action, AuditAction::Update);
+}
+
+#[test]
+fn disabled_log_records_nothing_and_refuses_queries() {
+    let log = AuditLog::open(None).unwrap();
+    log.record(&event(1, "components/echo", AuditAction::Push));
+    assert!(log.query(None, None, None).is_err());
+}
@@ -0,0 +1,328 @@
+// Utilitaires partagés par les tests qui passent par un registre OCI
+#![allow(dead_code)]
+
+use actix_web::http::Method;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use poc::audit::AuditLog;
+use poc::config::AppConfig;
+use poc::entities::{AppState, RegistryCredentials};
+use poc::events::EventBus;
+use poc::gc::BlobLedger;
+use poc::health::HealthCache;
+use poc::idempotency::IdempotencyStore;
+use poc::jobs::JobStore;
+use poc::jwt::JwksCache;
+use poc::manifest_builder::{CONFIG_MEDIA_TYPE, MANIFEST_MEDIA_TYPE, WASM_LAYER_MEDIA_TYPE};
+use poc::resilience;
+use poc::services::calculate_sha256;
+use poc::webhooks::WebhookQueue;
+use serde_json::json;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::TempDir;
+
+#[derive(Default)]
+struct Store {
+    // (dépôt, digest) -> (type de média, corps)
+    manifests: HashMap<(String, String), (String, Vec<u8>)>,
+    // (dépôt, tag) -> digest
+    tags: BTreeMap<(String, String), String>,
+    blobs: BTreeSet<(String, String)>,
+    uploads: u64,
+    // Envois de blobs refusés (400)
+    refuse_uploads: bool,
+    // Durée de chaque envoi de blob, pour observer leur parallélisme
+    upload_delay: Duration,
+    active_uploads: usize,
+    max_active_uploads: usize,
+}
+
+impl Store {
+    fn resolve(&self, repository: &str, reference: &str) -> Option<String> {
+        if reference.contains(':') {
+            let key = (repository.to_string(), reference.to_string());
+            return self
+                .manifests
+                .contains_key(&key)
+                .then(|| reference.to_string());
+        }
+        self.tags
+            .get(&(repository.to_string(), reference.to_string()))
+            .cloned()
+    }
+}
+
+// Registre OCI en mémoire : manifests, tags et blobs, avec la sémantique de suppression de Zot
+// (par tag, seul le tag disparaît ; par digest, le manifest et ses tags). Les blobs sont reçus
+// par un PUT monolithique sur la session ouverte par POST, digest vérifié
+#[derive(Clone)]
+pub struct FakeRegistry {
+    pub url: String,
+    store: Arc<Mutex<Store>>,
+}
+
+impl FakeRegistry {
+    pub async fn start() -> Self {
+        let store = Arc::new(Mutex::new(Store::default()));
+        let data = web::Data::new(store.clone());
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(data.clone())
+                .app_data(web::PayloadConfig::new(16 * 1024 * 1024))
+                .default_service(web::to(handle))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let url = format!("http://{}", server.addrs()[0]);
+        actix_web::rt::spawn(server.run());
+        FakeRegistry { url, store }
+    }
+
+    pub fn add_blob(&self, repository: &str, content: &[u8]) -> String {
+        let digest = calculate_sha256(content);
+        self.store
+            .lock()
+            .unwrap()
+            .blobs
+            .insert((repository.to_string(), digest.clone()));
+        digest
+    }
+
+    pub fn tag(&self, repository: &str, tag: &str, media_type: &str, raw: &[u8]) -> String {
+        let digest = calculate_sha256(raw);
+        let mut store = self.store.lock().unwrap();
+        store.manifests.insert(
+            (repository.to_string(), digest.clone()),
+            (media_type.to_string(), raw.to_vec()),
+        );
+        store
+            .tags
+            .insert((repository.to_string(), tag.to_string()), digest.clone());
+        digest
+    }
+
+    pub fn refuse_uploads(&self) {
+        self.store.lock().unwrap().refuse_uploads = true;
+    }
+
+    pub fn slow_uploads(&self, delay: Duration) {
+        self.store.lock().unwrap().upload_delay = delay;
+    }
+
+    // Plus grand nombre d'envois de blobs reçus simultanément
+    pub fn max_active_uploads(&self) -> usize {
+        self.store.lock().unwrap().max_active_uploads
+    }
+
+    pub fn tags(&self, repository: &str) -> Vec<String> {
+        self.store
+            .lock()
+            .unwrap()
+            .tags
+            .keys()
+            .filter(|(r, _)| r == repository)
+            .map(|(_, tag)| tag.clone())
+            .collect()
+    }
+
+    pub fn has_manifest(&self, repository: &str, digest: &str) -> bool {
+        self.store
+            .lock()
+            .unwrap()
+            .manifests
+            .contains_key(&(repository.to_string(), digest.to_string()))
+    }
+
+    pub fn has_blob(&self, repository: &str, digest: &str) -> bool {
+        self.store
+            .lock()
+            .unwrap()
+            .blobs
+            .contains(&(repository.to_string(), digest.to_string()))
+    }
+}
+
+// Manifest de composant minimal : une config et une couche wasm, poussées comme blobs
+pub fn component(registry: &FakeRegistry, repository: &str, content: &str) -> Vec<u8> {
+    let config = format!("{{\"content\":\"{}\"}}", content);
+    let layer = format!("\0asm{}", content);
+    let manifest = json!({
+        "schemaVersion": 2,
+        "mediaType": MANIFEST_MEDIA_TYPE,
+        "config": {
+            "mediaType": CONFIG_MEDIA_TYPE,
+            "digest": registry.add_blob(repository, config.as_bytes()),
+            "size": config.len()
+        },
+        "layers": [{
+            "mediaType": WASM_LAYER_MEDIA_TYPE,
+            "digest": registry.add_blob(repository, layer.as_bytes()),
+            "size": layer.len()
+        }]
+    });
+    serde_json::to_vec(&manifest).unwrap()
+}
+
+async fn handle(
+    request: HttpRequest,
+    body: web::Bytes,
+    store: web::Data<Arc<Mutex<Store>>>,
+) -> HttpResponse {
+    let path = request.path().trim_start_matches("/v2/").to_string();
+
+    if *request.method() == Method::PUT && path.contains("/blobs/uploads/") {
+        let delay = {
+            let mut store = store.lock().unwrap();
+            store.active_uploads += 1;
+            store.max_active_uploads = store.max_active_uploads.max(store.active_uploads);
+            store.upload_delay
+        };
+        actix_web::rt::time::sleep(delay).await;
+        store.lock().unwrap().active_uploads -= 1;
+    }
+    let mut store = store.lock().unwrap();
+
+    if path.is_empty() {
+        return HttpResponse::Ok().finish();
+    }
+    if path == "_catalog" {
+        let repositories: BTreeSet<&String> = store.tags.keys().map(|(r, _)| r).collect();
+        return HttpResponse::Ok().json(json!({ "repositories": repositories }));
+    }
+    if let Some(repository) = path.strip_suffix("/tags/list") {
+        let tags: Vec<&String> = store
+            .tags
+            .keys()
+            .filter(|(r, _)| r == repository)
+            .map(|(_, tag)| tag)
+            .collect();
+        return HttpResponse::Ok().json(json!({ "name": repository, "tags": tags }));
+    }
+
+    if let Some((repository, reference)) = path.rsplit_once("/manifests/") {
+        let (repository, reference) = (repository.to_string(), reference.to_string());
+        return match *request.method() {
+            Method::GET | Method::HEAD => match store.resolve(&repository, &reference) {
+                Some(digest) => {
+                    let (media_type, raw) = &store.manifests[&(repository, digest.clone())];
+                    HttpResponse::Ok()
+                        .content_type(media_type.as_str())
+                        .insert_header(("Docker-Content-Digest", digest))
+                        .body(raw.clone())
+                }
+                None => HttpResponse::NotFound().finish(),
+            },
+            Method::PUT => {
+                let digest = calculate_sha256(&body);
+                let media_type = request
+                    .headers()
+                    .get("Content-Type")
+                    .and_then(|value| value.to_str().ok())
+                    .unwrap_or(MANIFEST_MEDIA_TYPE)
+                    .to_string();
+                store.manifests.insert(
+                    (repository.clone(), digest.clone()),
+                    (media_type, body.to_vec()),
+                );
+                if !reference.contains(':') {
+                    store.tags.insert((repository, reference), digest.clone());
+                }
+                HttpResponse::Created()
+                    .insert_header(("Docker-Content-Digest", digest))
+                    .finish()
+            }
+            Method::DELETE if reference.contains(':') => {
+                let key = (repository.clone(), reference.clone());
+                if store.manifests.remove(&key).is_none() {
+                    return HttpResponse::NotFound().finish();
+                }
+                store
+                    .tags
+                    .retain(|(r, _), digest| *r != repository || *digest != reference);
+                HttpResponse::Accepted().finish()
+            }
+            Method::DELETE => match store.tags.remove(&(repository, reference)) {
+                Some(_) => HttpResponse::Accepted().finish(),
+                None => HttpResponse::NotFound().finish(),
+            },
+            _ => HttpResponse::MethodNotAllowed().finish(),
+        };
+    }
+
+    if let Some((repository, session)) = path.split_once("/blobs/uploads/") {
+        if *request.method() == Method::POST {
+            store.uploads += 1;
+            let location = format!("/v2/{}/blobs/uploads/{}", repository, store.uploads);
+            return HttpResponse::Accepted()
+                .insert_header(("Location", location))
+                .finish();
+        }
+        let query = web::Query::<HashMap<String, String>>::from_query(request.query_string());
+        let digest = query.ok().and_then(|query| query.get("digest").cloned());
+        if session.is_empty() || store.refuse_uploads {
+            return HttpResponse::BadRequest().finish();
+        }
+        return match digest {
+            Some(digest) if digest == calculate_sha256(&body) => {
+                store.blobs.insert((repository.to_string(), digest));
+                HttpResponse::Created().finish()
+            }
+            _ => HttpResponse::BadRequest().body("digest invalide"),
+        };
+    }
+
+    if let Some((repository, digest)) = path.rsplit_once("/blobs/") {
+        let key = (repository.to_string(), digest.to_string());
+        return match *request.method() {
+            Method::HEAD if store.blobs.contains(&key) => HttpResponse::Ok().finish(),
+            Method::DELETE if store.blobs.remove(&key) => HttpResponse::Accepted().finish(),
+            _ => HttpResponse::NotFound().finish(),
+        };
+    }
+
+    HttpResponse::NotFound().finish()
+}
+
+pub fn credentials() -> RegistryCredentials {
+    RegistryCredentials::Basic {
+        username: "admin".to_string(),
+        password: "admin".to_string(),
+    }
+}
+
+// État du service pointant sur le registre, ses données persistées dans `directory`
+pub fn app_state(registry: &FakeRegistry, directory: &TempDir, config: AppConfig) -> AppState {
+    let data_dir = directory.path();
+    let mut config = config;
+    config.zot.url = registry.url.clone();
+
+    AppState {
+        registry_client: Mutex::new(resilience::build_client(&config.upstream).unwrap()),
+        http_client: resilience::build_http_client().unwrap(),
+        zot_config: config.zot,
+        trash_config: config.trash,
+        gc_config: config.gc,
+        history_config: config.history,
+        blob_ledger: BlobLedger::open(data_dir.join("gc-ledger.json")).unwrap(),
+        retention_config: config.retention,
+        auth_config: config.auth,
+        jwks_cache: JwksCache::default(),
+        audit_log: AuditLog::open(Some(data_dir.join("audit.jsonl"))).unwrap(),
+        webhook_config: config.webhooks,
+        webhook_queue: WebhookQueue::open(data_dir.join("webhooks.json")).unwrap(),
+        event_bus: EventBus::new(config.events.buffer_size),
+        events_config: config.events,
+        health_config: config.health,
+        health_cache: HealthCache::default(),
+        upload_config: config.uploads,
+        idempotency_store: IdempotencyStore::open(
+            data_dir.join("idempotency.json"),
+            config.idempotency.window_hours,
+        )
+        .unwrap(),
+        job_store: JobStore::open(data_dir.join("jobs.json"), config.jobs.max_concurrent).unwrap(),
+        jobs_config: config.jobs,
+    }
+}
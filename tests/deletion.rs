@@ -0,0 +1,161 @@
+mod common;
+
+use common::{app_state, component, credentials, FakeRegistry};
+use poc::config::AppConfig;
+use poc::deletion::{delete_all_versions, delete_version, DeleteError, DeleteReport};
+use poc::manifest_builder::{INDEX_MEDIA_TYPE, MANIFEST_MEDIA_TYPE};
+use poc::services::calculate_sha256;
+use serde_json::json;
+use tempfile::TempDir;
+
+const REPOSITORY: &str = "components/echo";
+
+// Index `_history.` listant des manifests déjà présents dans le registre
+fn history(manifests: &[&[u8]]) -> Vec<u8> {
+    let descriptors: Vec<_> = manifests
+        .iter()
+        .map(|raw| {
+            json!({
+                "mediaType": MANIFEST_MEDIA_TYPE,
+                "digest": calculate_sha256(raw),
+                "size": raw.len()
+            })
+        })
+        .collect();
+    serde_json::to_vec(&json!({
+        "schemaVersion": 2,
+        "mediaType": INDEX_MEDIA_TYPE,
+        "manifests": descriptors
+    }))
+    .unwrap()
+}
+
+fn blobs_of(raw: &[u8]) -> Vec<String> {
+    let value: serde_json::Value = serde_json::from_slice(raw).unwrap();
+    let mut blobs = vec![value["config"]["digest"].as_str().unwrap().to_string()];
+    blobs.push(value["layers"][0]["digest"].as_str().unwrap().to_string());
+    blobs
+}
+
+fn sorted(mut items: Vec<String>) -> Vec<String> {
+    items.sort();
+    items
+}
+
+fn report(result: Result<DeleteReport, DeleteError>) -> DeleteReport {
+    match result {
+        Ok(report) => report,
+        Err(DeleteError::NotFound) => panic!("version introuvable"),
+        Err(DeleteError::Referenced(tags)) => panic!("version référencée par {:?}", tags),
+        Err(DeleteError::Registry(e)) => panic!("erreur registre: {}", e.message),
+    }
+}
+
+#[actix_web::test]
+async fn deleting_a_version_reports_what_became_unreachable() {
+    let registry = FakeRegistry::start().await;
+    let directory = TempDir::new().unwrap();
+    let state = app_state(&registry, &directory, AppConfig::default());
+
+    let old = component(&registry, REPOSITORY, "1.0.0-a");
+    let current = component(&registry, REPOSITORY, "1.0.0-b");
+    let other = component(&registry, REPOSITORY, "2.0.0");
+    let old_digest = registry.tag(REPOSITORY, "old", MANIFEST_MEDIA_TYPE, &old);
+    registry.tag(REPOSITORY, "1.0.0", MANIFEST_MEDIA_TYPE, &current);
+    let other_digest = registry.tag(REPOSITORY, "2.0.0", MANIFEST_MEDIA_TYPE, &other);
+    let index = history(&[&old, &current]);
+    let index_digest = registry.tag(REPOSITORY, "_history.1.0.0", INDEX_MEDIA_TYPE, &index);
+    // Une version dont le manifest n'est plus gardé que par l'historique d'une autre
+    let removed = report(delete_version(&state, &credentials(), REPOSITORY, "old", false).await);
+    assert_eq!(removed.tags, vec!["old".to_string()]);
+    // Toujours référencé par `_history.1.0.0` : le manifest et ses blobs restent
+    assert!(removed.manifests.is_empty());
+    assert!(removed.blobs.is_empty());
+    assert!(registry.has_manifest(REPOSITORY, &old_digest));
+
+    let removed = report(delete_version(&state, &credentials(), REPOSITORY, "1.0.0", false).await);
+    assert_eq!(
+        removed.tags,
+        vec!["1.0.0".to_string(), "_history.1.0.0".to_string()]
+    );
+    // L'index d'abord, puis les manifests qu'il était seul à garder
+    assert_eq!(removed.manifests[0], index_digest);
+    assert_eq!(
+        sorted(removed.manifests[1..].to_vec()),
+        sorted(vec![old_digest.clone(), calculate_sha256(&current)])
+    );
+    assert_eq!(
+        removed.blobs,
+        sorted([blobs_of(&old), blobs_of(&current)].concat())
+    );
+
+    // L'autre version n'est pas touchée
+    assert_eq!(registry.tags(REPOSITORY), vec!["2.0.0".to_string()]);
+    assert!(registry.has_manifest(REPOSITORY, &other_digest));
+    for blob in blobs_of(&other) {
+        assert!(registry.has_blob(REPOSITORY, &blob));
+    }
+    assert!(!registry.has_manifest(REPOSITORY, &old_digest));
+}
+
+#[actix_web::test]
+async fn shared_manifest_is_refused_or_deleted_with_all_its_tags() {
+    let registry = FakeRegistry::start().await;
+    let directory = TempDir::new().unwrap();
+    let state = app_state(&registry, &directory, AppConfig::default());
+
+    let manifest = component(&registry, REPOSITORY, "1.0.0");
+    let digest = registry.tag(REPOSITORY, "1.0.0", MANIFEST_MEDIA_TYPE, &manifest);
+    registry.tag(REPOSITORY, "latest", MANIFEST_MEDIA_TYPE, &manifest);
+
+    match delete_version(&state, &credentials(), REPOSITORY, "1.0.0", true).await {
+        Err(DeleteError::Referenced(tags)) => assert_eq!(tags, vec!["latest".to_string()]),
+        _ => panic!("suppression d'un manifest partagé acceptée"),
+    }
+    assert_eq!(registry.tags(REPOSITORY).len(), 2);
+
+    let removed = report(delete_version(&state, &credentials(), REPOSITORY, "1.0.0", false).await);
+    assert_eq!(
+        removed.tags,
+        vec!["1.0.0".to_string(), "latest".to_string()]
+    );
+    assert_eq!(removed.manifests, vec![digest]);
+    assert_eq!(removed.blobs, sorted(blobs_of(&manifest)));
+    assert!(registry.tags(REPOSITORY).is_empty());
+}
+
+#[actix_web::test]
+async fn deleting_all_versions_empties_the_repository() {
+    let registry = FakeRegistry::start().await;
+    let directory = TempDir::new().unwrap();
+    let state = app_state(&registry, &directory, AppConfig::default());
+
+    let first = component(&registry, REPOSITORY, "1.0.0");
+    let second = component(&registry, REPOSITORY, "2.0.0");
+    registry.tag(REPOSITORY, "1.0.0", MANIFEST_MEDIA_TYPE, &first);
+    registry.tag(REPOSITORY, "2.0.0", MANIFEST_MEDIA_TYPE, &second);
+    registry.tag(
+        REPOSITORY,
+        "_history.2.0.0",
+        INDEX_MEDIA_TYPE,
+        &history(&[&second]),
+    );
+
+    let removed = report(delete_all_versions(&state, &credentials(), REPOSITORY).await);
+    assert_eq!(removed.tags.len(), 3);
+    assert_eq!(removed.manifests.len(), 3);
+    assert_eq!(
+        removed.blobs,
+        sorted([blobs_of(&first), blobs_of(&second)].concat())
+    );
+    assert!(registry.tags(REPOSITORY).is_empty());
+
+    assert!(matches!(
+        delete_all_versions(&state, &credentials(), REPOSITORY).await,
+        Err(DeleteError::NotFound)
+    ));
+    assert!(matches!(
+        delete_version(&state, &credentials(), REPOSITORY, "1.0.0", false).await,
+        Err(DeleteError::NotFound)
+    ));
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use actix_web::body::MessageBody;
+use actix_web::test::{call_service, init_service, TestRequest};
+use actix_web::{web, App};
+use chrono::Utc;
+use common::{app_state, FakeRegistry};
+use poc::config::AppConfig;
+use poc::controllers::stream_events::stream_events;
+use poc::events::EventBus;
+use poc::webhooks::{WebhookEvent, WebhookPayload};
+use std::time::Duration;
+use tempfile::TempDir;
+
+fn payload(event: WebhookEvent, repository: &str, version: &str) -> WebhookPayload {
+    WebhookPayload {
+        event,
+        timestamp: Utc::now(),
+        repository: repository.to_string(),
+        version: Some(version.to_string()),
+        digest: None,
+        component_type: Some("filter".to_string()),
+        actor: "ci".to_string(),
+    }
+}
+
+fn versions(bus: &EventBus, last_id: u64) -> Vec<String> {
+    bus.since(last_id)
+        .into_iter()
+        .map(|event| event.payload.version.unwrap())
+        .collect()
+}
+
+#[test]
+fn replay_starts_after_the_last_event_id() {
+    let bus = EventBus::new(10);
+    let start = bus.latest_id();
+    assert!(bus.since(start).is_empty());
+
+    bus.publish(payload(WebhookEvent::Published, "echo", "1.0.0"));
+    let first = bus.latest_id();
+    bus.publish(payload(WebhookEvent::Updated, "echo", "1.0.1"));
+    bus.publish(payload(WebhookEvent::Deleted, "echo", "1.0.0"));
+
+    assert_eq!(first, start + 1);
+    assert_eq!(versions(&bus, start), vec!["1.0.0", "1.0.1", "1.0.0"]);
+    assert_eq!(versions(&bus, first), vec!["1.0.1", "1.0.0"]);
+    assert!(bus.since(bus.latest_id()).is_empty());
+}
+
+#[test]
+fn buffer_keeps_only_the_latest_events() {
+    let bus = EventBus::new(2);
+    let start = bus.latest_id();
+    for version in ["1", "2", "3"] {
+        bus.publish(payload(WebhookEvent::Published, "echo", version));
+    }
+    // Le premier événement est sorti du tampon : il n'est plus rejouable
+    assert_eq!(versions(&bus, start), vec!["2", "3"]);
+    assert_eq!(bus.latest_id(), start + 3);
+}
+
+// Prochain bloc du flux SSE, en attendant au plus une seconde
+async fn next_chunk<B: MessageBody + Unpin>(body: &mut B) -> String {
+    let chunk = tokio::time::timeout(
+        Duration::from_secs(1),
+        futures::future::poll_fn(|cx| std::pin::Pin::new(&mut *body).poll_next(cx)),
+    )
+    .await
+    .expect("aucun événement reçu");
+    let chunk = chunk.unwrap().ok().unwrap();
+    String::from_utf8(chunk.to_vec()).unwrap()
+}
+
+#[actix_web::test]
+async fn stream_replays_missed_events_matching_the_filter() {
+    let registry = FakeRegistry::start().await;
+    let directory = TempDir::new().unwrap();
+    let state = web::Data::new(app_state(&registry, &directory, AppConfig::default()));
+    let app = init_service(App::new().app_data(state.clone()).service(stream_events)).await;
+
+    let start = state.event_bus.latest_id();
+    state
+        .event_bus
+        .publish(payload(WebhookEvent::Published, "echo", "1.0.0"));
+    state
+        .event_bus
+        .publish(payload(WebhookEvent::Published, "filter", "2.0.0"));
+    state
+        .event_bus
+        .publish(payload(WebhookEvent::Updated, "echo", "1.0.1"));
+
+    let request = TestRequest::get()
+        .uri("/api/v1/events?repository=echo")
+        .insert_header(("Last-Event-ID", start.to_string()))
+        .to_request();
+    let response = call_service(&app, request).await;
+    assert_eq!(
+        response.headers().get("Content-Type").unwrap(),
+        "text/event-stream"
+    );
+    let mut body = response.into_body();
+
+    let first = next_chunk(&mut body).await;
+    assert!(first.starts_with(&format!("id: {}\nevent: component.published\n", start + 1)));
+    assert!(first.contains("\"version\":\"1.0.0\""));
+    // L'événement du dépôt `filter` est écarté, sans retarder les suivants
+    let second = next_chunk(&mut body).await;
+    assert!(second.starts_with(&format!("id: {}\nevent: component.updated\n", start + 3)));
+}
+
+#[actix_web::test]
+async fn stream_without_last_event_id_only_sends_new_events() {
+    let registry = FakeRegistry::start().await;
+    let directory = TempDir::new().unwrap();
+    let state = web::Data::new(app_state(&registry, &directory, AppConfig::default()));
+    let app = init_service(App::new().app_data(state.clone()).service(stream_events)).await;
+
+    state
+        .event_bus
+        .publish(payload(WebhookEvent::Published, "echo", "1.0.0"));
+    let request = TestRequest::get().uri("/api/v1/events").to_request();
+    let mut body = call_service(&app, request).await.into_body();
+
+    state
+        .event_bus
+        .publish(payload(WebhookEvent::Deleted, "echo", "1.0.0"));
+    let chunk = next_chunk(&mut body).await;
+    assert!(chunk.starts_with(&format!(
+        "id: {}\nevent: component.deleted\n",
+        state.event_bus.latest_id()
+    )));
+}
@@ -0,0 +1,166 @@
+use actix_web::body::to_bytes;
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use actix_web::{HttpRequest, HttpResponse};
+use futures::channel::oneshot;
+use poc::auth::Caller;
+use poc::entities::RegistryCredentials;
+use poc::idempotency::{idempotent, IdempotencyStore, REPLAYED_HEADER};
+use std::cell::Cell;
+use std::time::Duration;
+use tempfile::TempDir;
+
+fn caller(name: &str) -> Caller {
+    Caller {
+        name: name.to_string(),
+        grants: Vec::new(),
+        credentials: RegistryCredentials::Bearer("token".to_string()),
+    }
+}
+
+fn request(key: &str) -> HttpRequest {
+    TestRequest::post()
+        .uri("/api/v1/components")
+        .insert_header(("Idempotency-Key", key))
+        .to_http_request()
+}
+
+fn open(directory: &TempDir) -> IdempotencyStore {
+    IdempotencyStore::open(directory.path().join("idempotency.json"), 24).unwrap()
+}
+
+async fn body(response: HttpResponse) -> String {
+    let body = to_bytes(response.into_body()).await.unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[actix_web::test]
+async fn same_request_replays_the_first_response() {
+    let directory = TempDir::new().unwrap();
+    let store = open(&directory);
+    let caller = caller("ci");
+    let executions = Cell::new(0);
+    let publish = || async {
+        executions.set(executions.get() + 1);
+        HttpResponse::Created().body("publié")
+    };
+
+    let first = idempotent(&store, &request("k1"), &caller, "a", publish()).await;
+    assert_eq!(first.status(), StatusCode::CREATED);
+    assert!(first.headers().get(REPLAYED_HEADER).is_none());
+
+    let second = idempotent(&store, &request("k1"), &caller, "a", publish()).await;
+    assert_eq!(second.status(), StatusCode::CREATED);
+    assert!(second.headers().get(REPLAYED_HEADER).is_some());
+    assert_eq!(body(second).await, "publié");
+    assert_eq!(executions.get(), 1);
+
+    // La réponse survit au redémarrage du service
+    drop(store);
+    let store = open(&directory);
+    let replayed = idempotent(&store, &request("k1"), &caller, "a", publish()).await;
+    assert!(replayed.headers().get(REPLAYED_HEADER).is_some());
+    assert_eq!(executions.get(), 1);
+}
+
+#[actix_web::test]
+async fn keys_are_scoped_by_caller() {
+    let directory = TempDir::new().unwrap();
+    let store = open(&directory);
+    let executions = Cell::new(0);
+    let publish = || async {
+        executions.set(executions.get() + 1);
+        HttpResponse::Created().finish()
+    };
+
+    idempotent(&store, &request("k1"), &caller("a"), "a", publish()).await;
+    let other = idempotent(&store, &request("k1"), &caller("b"), "a", publish()).await;
+    assert!(other.headers().get(REPLAYED_HEADER).is_none());
+    assert_eq!(executions.get(), 2);
+}
+
+#[actix_web::test]
+async fn reused_key_with_another_request_is_rejected() {
+    let directory = TempDir::new().unwrap();
+    let store = open(&directory);
+    let caller = caller("ci");
+
+    idempotent(&store, &request("k1"), &caller, "a", async {
+        HttpResponse::Created().finish()
+    })
+    .await;
+    let mismatch = idempotent(&store, &request("k1"), &caller, "b", async {
+        unreachable!("la requête différente ne doit pas être exécutée")
+    })
+    .await;
+    assert_eq!(mismatch.status(), StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[actix_web::test]
+async fn concurrent_request_with_same_key_conflicts() {
+    let directory = TempDir::new().unwrap();
+    let store = open(&directory);
+    let caller = caller("ci");
+    let (release, released) = oneshot::channel::<()>();
+    let req = request("k1");
+
+    let first = idempotent(&store, &req, &caller, "a", async {
+        released.await.unwrap();
+        HttpResponse::Created().finish()
+    });
+    let second = async {
+        let response = idempotent(&store, &request("k1"), &caller, "a", async {
+            unreachable!("la clé est déjà réservée")
+        })
+        .await;
+        release.send(()).unwrap();
+        response
+    };
+    let (first, second) = futures::join!(first, second);
+    assert_eq!(first.status(), StatusCode::CREATED);
+    assert_eq!(second.status(), StatusCode::CONFLICT);
+}
+
+#[actix_web::test]
+async fn abandoned_request_releases_its_key() {
+    let directory = TempDir::new().unwrap();
+    let store = open(&directory);
+    let caller = caller("ci");
+
+    // Client déconnecté : le futur de la requête est abandonné en cours d'exécution
+    let abandoned = tokio::time::timeout(
+        Duration::from_millis(20),
+        idempotent(&store, &request("k1"), &caller, "a", async {
+            futures::future::pending::<HttpResponse>().await
+        }),
+    )
+    .await;
+    assert!(abandoned.is_err());
+
+    let retry = idempotent(&store, &request("k1"), &caller, "a", async {
+        HttpResponse::Created().finish()
+    })
+    .await;
+    assert_eq!(retry.status(), StatusCode::CREATED);
+    assert!(retry.headers().get(REPLAYED_HEADER).is_none());
+}
+
+#[actix_web::test]
+async fn server_errors_are_not_replayed() {
+    let directory = TempDir::new().unwrap();
+    let store = open(&directory);
+    let caller = caller("ci");
+
+    let failed = idempotent(&store, &request("k1"), &caller, "a", async {
+        HttpResponse::InternalServerError().finish()
+    })
+    .await;
+    assert_eq!(failed.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let retry = idempotent(&store, &request("k1"), &caller, "a", async {
+        HttpResponse::Created().finish()
+    })
+    .await;
+    assert_eq!(retry.status(), StatusCode::CREATED);
+    assert!(retry.headers().get(REPLAYED_HEADER).is_none());
+}
@@ -0,0 +1,174 @@
+mod common;
+
+use actix_web::body::MessageBody;
+use actix_web::http::StatusCode;
+use actix_web::web;
+use chrono::{TimeZone, Utc};
+use common::{app_state, credentials, FakeRegistry};
+use poc::auth::Caller;
+use poc::config::{AppConfig, JobsConfig};
+use poc::entities::{AppState, ManifestMetadata};
+use poc::jobs::{submit_push, Job, JobState, JobStore};
+use poc::manifest_builder::wasm_layer_of;
+use poc::services::calculate_sha256;
+use poc::upload::{ComponentUpload, SpooledWasm};
+use serde_json::json;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+use tempfile::{NamedTempFile, TempDir};
+
+const WASM: &[u8] = b"\0asm\x01\0\0\0";
+
+fn caller() -> Caller {
+    Caller {
+        name: "ci".to_string(),
+        grants: Vec::new(),
+        credentials: credentials(),
+    }
+}
+
+fn upload() -> ComponentUpload {
+    let path =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/component-minimal.json");
+    let manifest: ManifestMetadata = serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap();
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(WASM).unwrap();
+    ComponentUpload {
+        manifest: Some(manifest),
+        wasm: Some(SpooledWasm {
+            layer: wasm_layer_of(calculate_sha256(WASM), WASM.len() as u64),
+            file,
+        }),
+        attachments: Vec::new(),
+    }
+}
+
+fn submit(state: &web::Data<AppState>) -> (StatusCode, Job) {
+    let created = Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap();
+    let response = submit_push(state, &caller(), upload(), created);
+    let status = response.status();
+    let body = match response.into_body().try_into_bytes() {
+        Ok(body) => body,
+        Err(_) => panic!("corps de réponse attendu en mémoire"),
+    };
+    let job = serde_json::from_slice(&body)
+        .unwrap_or_else(|_| panic!("{}: {}", status, String::from_utf8_lossy(&body)));
+    (status, job)
+}
+
+// Attend la fin de la tâche
+async fn finished(state: &AppState, id: &str) -> Job {
+    for _ in 0..500 {
+        let job = state.job_store.get(id).unwrap();
+        if matches!(job.state, JobState::Succeeded | JobState::Failed) {
+            return job;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("tâche {} toujours en cours", id);
+}
+
+#[actix_web::test]
+async fn submitted_push_runs_to_success() {
+    let registry = FakeRegistry::start().await;
+    let directory = TempDir::new().unwrap();
+    let state = web::Data::new(app_state(&registry, &directory, AppConfig::default()));
+
+    let (status, job) = submit(&state);
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(job.state, JobState::Queued);
+    assert_eq!(
+        (job.repository.as_str(), job.reference.as_str()),
+        ("echo", "0.1.0")
+    );
+    assert!(job.blobs.is_empty());
+
+    let job = finished(&state, &job.id).await;
+    assert_eq!(job.state, JobState::Succeeded);
+    assert!(job.error.is_none());
+    // Config et binaire, entièrement transmis
+    assert_eq!(job.blobs.len(), 2);
+    assert!(job.blobs.iter().all(|blob| blob.sent == blob.size));
+    assert!(registry.has_blob("echo", &calculate_sha256(WASM)));
+    assert!(registry.tags("echo").contains(&"0.1.0".to_string()));
+    assert!(registry.has_manifest("echo", job.digest.as_ref().unwrap()));
+}
+
+#[actix_web::test]
+async fn refused_upload_fails_the_job() {
+    let registry = FakeRegistry::start().await;
+    registry.refuse_uploads();
+    let directory = TempDir::new().unwrap();
+    let state = web::Data::new(app_state(&registry, &directory, AppConfig::default()));
+
+    let (_, job) = submit(&state);
+    let job = finished(&state, &job.id).await;
+    assert_eq!(job.state, JobState::Failed);
+    assert!(job.error.is_some());
+    assert!(job.digest.is_none());
+    assert!(registry.tags("echo").is_empty());
+}
+
+#[actix_web::test]
+async fn full_queue_refuses_new_pushes() {
+    let registry = FakeRegistry::start().await;
+    let directory = TempDir::new().unwrap();
+    let config = AppConfig {
+        jobs: JobsConfig {
+            max_queued: 1,
+            ..JobsConfig::default()
+        },
+        ..AppConfig::default()
+    };
+    let state = web::Data::new(app_state(&registry, &directory, config));
+
+    // La première tâche n'a pas encore démarré : elle occupe la file
+    let (_, job) = submit(&state);
+    let response = submit_push(&state, &caller(), upload(), Utc::now());
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.headers().get("Retry-After").unwrap(), "30");
+
+    finished(&state, &job.id).await;
+    assert_eq!(submit(&state).0, StatusCode::ACCEPTED);
+}
+
+#[test]
+fn interrupted_jobs_fail_on_restart() {
+    let directory = TempDir::new().unwrap();
+    let path = directory.path().join("jobs.json");
+    let job = |id: &str, state: &str| {
+        json!({
+            "id": id,
+            "caller": "ci",
+            "repository": "echo",
+            "reference": "0.1.0",
+            "state": state,
+            "created_at": "2025-03-01T12:00:00Z",
+            "updated_at": "2025-03-01T12:00:00Z",
+            "blobs": [],
+            "digest": null,
+            "error": null
+        })
+    };
+    let jobs = json!([
+        job("queued", "queued"),
+        job("running", "running"),
+        job("succeeded", "succeeded")
+    ]);
+    std::fs::write(&path, serde_json::to_vec(&jobs).unwrap()).unwrap();
+
+    let store = JobStore::open(path, 1).unwrap();
+    for id in ["queued", "running"] {
+        let job = store.get(id).unwrap();
+        assert_eq!(job.state, JobState::Failed);
+        assert_eq!(
+            job.error.as_deref(),
+            Some("Interrompue par l'arrêt du service")
+        );
+    }
+    let job = store.get("succeeded").unwrap();
+    assert_eq!(job.state, JobState::Succeeded);
+    assert!(job.error.is_none());
+    assert!(store.get("unknown").is_none());
+}
@@ -0,0 +1,135 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::{DateTime, TimeZone, Utc};
+use poc::entities::ManifestMetadata;
+use poc::manifest_builder::{canonical_json, config_content};
+use poc::publish::{plan_component, ConfigSource, LayerSource};
+use serde_json::{json, Value};
+
+const WASM: &[u8] = b"\0asm\x01\0\0\0";
+
+fn created() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap()
+}
+
+fn read_fixture(name: &str) -> Vec<u8> {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name);
+    fs::read(path).unwrap()
+}
+
+fn load_fixture(name: &str) -> ManifestMetadata {
+    serde_json::from_slice(&read_fixture(name)).unwrap()
+}
+
+// Réécrit le document avec les clés de chaque objet en ordre inverse
+fn reversed_keys(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| b.cmp(a));
+            let fields: Vec<String> = entries
+                .into_iter()
+                .map(|(key, value)| {
+                    format!("{}: {}", Value::from(key.as_str()), reversed_keys(value))
+                })
+                .collect();
+            format!("{{ {} }}", fields.join(", "))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(reversed_keys).collect();
+            format!("[{}]", items.join(", "))
+        }
+        scalar => scalar.to_string(),
+    }
+}
+
+fn plan_digest(metadata: &ManifestMetadata, created: DateTime<Utc>) -> (Vec<u8>, String) {
+    let plan = plan_component(
+        metadata,
+        ConfigSource::Upload,
+        LayerSource::Upload(WASM, &[]),
+        created,
+    )
+    .unwrap();
+    let digest = poc::services::calculate_sha256(&plan.body);
+    (plan.body, digest)
+}
+
+// Octets exacts du manifest poussé, dont le digest dépend
+fn render(fixture: &str) -> Vec<u8> {
+    plan_digest(&load_fixture(fixture), created()).0
+}
+
+// UPDATE_GOLDEN=1 cargo test pour régénérer les fichiers de référence
+fn assert_golden(fixture: &str, golden: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(golden);
+    let actual = render(fixture);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, &actual).unwrap();
+    }
+    let expected = fs::read(&path).unwrap();
+    assert!(
+        actual == expected,
+        "manifest différent de {}:\n{}",
+        path.display(),
+        String::from_utf8_lossy(&actual)
+    );
+}
+
+#[test]
+fn full_component_matches_golden() {
+    assert_golden("component.json", "component.manifest.json");
+}
+
+#[test]
+fn minimal_component_matches_golden() {
+    assert_golden("component-minimal.json", "component-minimal.manifest.json");
+}
+
+#[test]
+fn identical_input_builds_identical_manifest() {
+    assert_eq!(render("component.json"), render("component.json"));
+}
+
+#[test]
+fn canonical_json_sorts_keys_without_whitespace() {
+    let value = json!({ "b": [{ "z": 1, "y": "é" }], "a": { "d": null, "c": true } });
+    assert_eq!(
+        String::from_utf8(canonical_json(&value)).unwrap(),
+        r#"{"a":{"c":true,"d":null},"b":[{"y":"é","z":1}]}"#
+    );
+}
+
+#[test]
+fn config_content_ignores_source_key_order() {
+    let original: Value = serde_json::from_slice(&read_fixture("component.json")).unwrap();
+    let reordered: ManifestMetadata = serde_json::from_str(&reversed_keys(&original)).unwrap();
+    assert_eq!(
+        config_content(&load_fixture("component.json")),
+        config_content(&reordered)
+    );
+}
+
+#[test]
+fn identical_input_plans_identical_digest() {
+    let metadata = load_fixture("component.json");
+    let (body, digest) = plan_digest(&metadata, created());
+    assert_eq!(
+        plan_digest(&metadata, created()),
+        (body.clone(), digest.clone())
+    );
+
+    let reordered: ManifestMetadata = serde_json::from_str(&reversed_keys(
+        &serde_json::from_slice(&read_fixture("component.json")).unwrap(),
+    ))
+    .unwrap();
+    assert_eq!(plan_digest(&reordered, created()).1, digest);
+
+    let later = created() + chrono::Duration::seconds(1);
+    assert_ne!(plan_digest(&metadata, later).1, digest);
+}
@@ -0,0 +1,103 @@
+use poc::services::apply_merge_patch;
+use serde_json::{json, Value};
+
+fn patched(target: Value, patch: Value) -> Value {
+    let mut target = target;
+    apply_merge_patch(&mut target, &patch);
+    target
+}
+
+// Exemples de l'annexe A de la RFC 7386
+#[test]
+fn rfc_7386_examples() {
+    let cases = [
+        (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+        (
+            json!({"a": "b"}),
+            json!({"b": "c"}),
+            json!({"a": "b", "b": "c"}),
+        ),
+        (json!({"a": "b"}), json!({"a": null}), json!({})),
+        (
+            json!({"a": "b", "b": "c"}),
+            json!({"a": null}),
+            json!({"b": "c"}),
+        ),
+        (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+        (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+        (
+            json!({"a": {"b": "c"}}),
+            json!({"a": {"b": "d", "c": null}}),
+            json!({"a": {"b": "d"}}),
+        ),
+        (
+            json!({"a": [{"b": "c"}]}),
+            json!({"a": [1]}),
+            json!({"a": [1]}),
+        ),
+        (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+        (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+        (json!({"a": "foo"}), json!(null), json!(null)),
+        (json!({"a": "foo"}), json!("bar"), json!("bar")),
+        (
+            json!({"e": null}),
+            json!({"a": 1}),
+            json!({"e": null, "a": 1}),
+        ),
+        (
+            json!([1, 2]),
+            json!({"a": "b", "c": null}),
+            json!({"a": "b"}),
+        ),
+        (
+            json!({}),
+            json!({"a": {"bb": {"ccc": null}}}),
+            json!({"a": {"bb": {}}}),
+        ),
+    ];
+    for (target, patch, expected) in cases {
+        assert_eq!(
+            patched(target.clone(), patch.clone()),
+            expected,
+            "{} + {}",
+            target,
+            patch
+        );
+    }
+}
+
+#[test]
+fn patch_updates_crd_metadata_and_keeps_the_rest() {
+    let crd = json!({
+        "apiVersion": "aneocorp.com/v1",
+        "kind": "Component",
+        "metadata": {
+            "name": "http-filter",
+            "annotations": {
+                "version": "1.2.0",
+                "description": "Filtre HTTP",
+                "icon": "filter"
+            }
+        },
+        "spec": {"type": "filter"}
+    });
+    let patch = json!({
+        "metadata": {"annotations": {"description": "Filtre HTTP v2", "icon": null}}
+    });
+
+    let result = patched(crd, patch);
+    assert_eq!(
+        result["metadata"]["annotations"]["description"],
+        "Filtre HTTP v2"
+    );
+    assert!(result["metadata"]["annotations"].get("icon").is_none());
+    assert_eq!(result["metadata"]["annotations"]["version"], "1.2.0");
+    assert_eq!(result["metadata"]["name"], "http-filter");
+    assert_eq!(result["spec"], json!({"type": "filter"}));
+}
+
+#[test]
+fn empty_patch_changes_nothing() {
+    let crd = json!({"metadata": {"name": "http-filter"}});
+    assert_eq!(patched(crd.clone(), json!({})), crd);
+}
@@ -0,0 +1,41 @@
+use actix_web::body::BoxBody;
+use actix_web::dev::ServiceResponse;
+use actix_web::test::TestRequest;
+use actix_web::HttpResponse;
+use poc::metrics::{track_request, METRICS};
+use std::time::Duration;
+
+fn in_flight() -> String {
+    String::from_utf8(METRICS.render())
+        .unwrap()
+        .lines()
+        .find(|line| line.starts_with("http_requests_in_flight "))
+        .unwrap()
+        .to_string()
+}
+
+#[actix_web::test]
+async fn abandoned_requests_leave_the_in_flight_gauge() {
+    assert_eq!(in_flight(), "http_requests_in_flight 0");
+
+    // Client déconnecté : la requête est abandonnée avant sa réponse
+    let abandoned = tokio::time::timeout(
+        Duration::from_millis(20),
+        track_request(
+            "GET".to_string(),
+            "/api/v1/{repository}/components".to_string(),
+            futures::future::pending::<Result<ServiceResponse<BoxBody>, actix_web::Error>>(),
+        ),
+    )
+    .await;
+    assert!(abandoned.is_err());
+    assert_eq!(in_flight(), "http_requests_in_flight 0");
+
+    let request = TestRequest::get().to_http_request();
+    let served = track_request("GET".to_string(), "/healthz".to_string(), async {
+        Ok(ServiceResponse::new(request, HttpResponse::Ok().finish()))
+    })
+    .await;
+    assert!(served.is_ok());
+    assert_eq!(in_flight(), "http_requests_in_flight 0");
+}
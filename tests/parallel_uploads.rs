@@ -0,0 +1,111 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::{app_state, credentials, FakeRegistry};
+use poc::config::{AppConfig, UploadConfig};
+use poc::entities::{ManifestMetadata, Operation};
+use poc::publish::{plan_component, push_plan, ConfigSource, LayerSource};
+use poc::services::{calculate_sha256, Progress};
+use poc::upload::Attachment;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::TempDir;
+
+const WASM: &[u8] = b"\0asm\x01\0\0\0";
+
+fn metadata() -> ManifestMetadata {
+    let path =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/component-minimal.json");
+    serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+}
+
+fn attachments() -> Vec<Attachment> {
+    (0..4)
+        .map(|n| Attachment {
+            name: format!("doc-{}.md", n),
+            media_type: "text/markdown".to_string(),
+            content: format!("# Documentation {}", n).into_bytes(),
+        })
+        .collect()
+}
+
+// Publie le composant avec quatre pièces jointes (six blobs) ; retourne le nombre maximal
+// d'envois simultanés observé par le registre et la dernière progression de chaque blob
+async fn publish(max_parallel_blobs: usize) -> (FakeRegistry, usize, HashMap<String, u64>) {
+    let registry = FakeRegistry::start().await;
+    registry.slow_uploads(Duration::from_millis(50));
+    let directory = TempDir::new().unwrap();
+    let config = AppConfig {
+        uploads: UploadConfig {
+            max_parallel_blobs,
+            ..UploadConfig::default()
+        },
+        ..AppConfig::default()
+    };
+    let state = app_state(&registry, &directory, config);
+
+    let metadata = metadata();
+    let attachments = attachments();
+    let created = Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap();
+    let plan = plan_component(
+        &metadata,
+        ConfigSource::Upload,
+        LayerSource::Upload(WASM, &attachments),
+        created,
+    )
+    .unwrap();
+    assert_eq!(plan.blobs.len(), 6);
+
+    let sent: Arc<Mutex<HashMap<String, u64>>> = Arc::default();
+    push_plan(
+        &state,
+        &credentials(),
+        "echo",
+        "0.1.0",
+        plan,
+        Operation::Push,
+        |blob| -> Progress {
+            let sent = sent.clone();
+            let digest = blob.digest.clone();
+            Arc::new(move |bytes| {
+                sent.lock().unwrap().insert(digest.clone(), bytes);
+            })
+        },
+    )
+    .await
+    .unwrap();
+
+    let max_active = registry.max_active_uploads();
+    let sent = sent.lock().unwrap().clone();
+    (registry, max_active, sent)
+}
+
+#[actix_web::test]
+async fn blob_uploads_are_bounded_by_the_pool_size() {
+    let (registry, max_active, sent) = publish(2).await;
+    assert_eq!(max_active, 2);
+
+    assert_eq!(sent.len(), 6);
+    assert_eq!(sent[&calculate_sha256(WASM)], WASM.len() as u64);
+    for attachment in attachments() {
+        let digest = calculate_sha256(&attachment.content);
+        assert!(registry.has_blob("echo", &digest));
+        assert_eq!(sent[&digest], attachment.content.len() as u64);
+    }
+    assert_eq!(registry.tags("echo"), vec!["0.1.0", "_history.0.1.0"]);
+}
+
+#[actix_web::test]
+async fn single_slot_pool_uploads_one_blob_at_a_time() {
+    let (registry, max_active, _) = publish(1).await;
+    assert_eq!(max_active, 1);
+    assert!(registry.tags("echo").contains(&"0.1.0".to_string()));
+}
+
+#[actix_web::test]
+async fn large_pool_uploads_every_blob_at_once() {
+    let (_, max_active, _) = publish(16).await;
+    assert_eq!(max_active, 6);
+}
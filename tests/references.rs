@@ -0,0 +1,27 @@
+use poc::deletion::{is_internal_tag, validate_reference};
+
+#[test]
+fn internal_tags_are_reserved() {
+    assert!(is_internal_tag("_history.1.0.0"));
+    assert!(is_internal_tag("_trash.1.0.0"));
+    assert!(validate_reference("_history.1.0.0").is_err());
+    assert!(validate_reference("_trash.1.0.0").is_err());
+    assert!(validate_reference("_anything").is_err());
+
+    assert!(validate_reference("1.0.0").is_ok());
+    assert!(validate_reference("latest").is_ok());
+    assert!(validate_reference("1.0.0_rc").is_ok());
+}
+
+#[test]
+fn history_and_trash_tags_stay_valid_oci_tags() {
+    // `_history.` compte 9 caractères : 119 au plus pour la référence
+    assert!(validate_reference(&"a".repeat(119)).is_ok());
+    assert!(validate_reference(&"a".repeat(120)).is_err());
+}
+
+#[test]
+fn digests_are_not_length_checked() {
+    let sha512 = format!("sha512:{}", "0".repeat(128));
+    assert!(validate_reference(&sha512).is_ok());
+}
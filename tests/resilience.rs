@@ -0,0 +1,120 @@
+use poc::config::UpstreamConfig;
+use poc::resilience::{backoff, blob_timeout, Breaker};
+use std::time::{Duration, Instant};
+
+fn config() -> UpstreamConfig {
+    UpstreamConfig {
+        failure_threshold: 3,
+        open_seconds: 30,
+        initial_backoff_ms: 200,
+        max_backoff_ms: 5000,
+        ..UpstreamConfig::default()
+    }
+}
+
+#[test]
+fn breaker_opens_after_consecutive_failures() {
+    let config = config();
+    let now = Instant::now();
+    let mut breaker = Breaker::default();
+
+    assert!(!breaker.record(&config, true, now));
+    assert!(!breaker.record(&config, true, now));
+    assert!(breaker.acquire(&config, now));
+    // Le troisième échec consécutif ouvre le disjoncteur
+    assert!(breaker.record(&config, true, now));
+    assert!(breaker.is_open(now));
+    assert!(!breaker.acquire(&config, now + Duration::from_secs(29)));
+}
+
+#[test]
+fn success_resets_the_failure_count() {
+    let config = config();
+    let now = Instant::now();
+    let mut breaker = Breaker::default();
+
+    breaker.record(&config, true, now);
+    breaker.record(&config, true, now);
+    breaker.record(&config, false, now);
+    assert!(!breaker.record(&config, true, now));
+    assert!(!breaker.record(&config, true, now));
+    assert!(!breaker.is_open(now));
+}
+
+#[test]
+fn half_open_breaker_lets_a_single_trial_through() {
+    let config = config();
+    let now = Instant::now();
+    let mut breaker = Breaker::default();
+    for _ in 0..3 {
+        breaker.record(&config, true, now);
+    }
+
+    let later = now + Duration::from_secs(30);
+    assert!(breaker.acquire(&config, later));
+    // Les appels suivants échouent tant que l'essai n'a pas abouti
+    assert!(!breaker.acquire(&config, later));
+
+    // Essai réussi : le disjoncteur se referme
+    breaker.record(&config, false, later);
+    assert!(!breaker.is_open(later));
+    assert!(breaker.acquire(&config, later));
+}
+
+#[test]
+fn failed_trial_reopens_the_breaker() {
+    let config = config();
+    let now = Instant::now();
+    let mut breaker = Breaker::default();
+    for _ in 0..3 {
+        breaker.record(&config, true, now);
+    }
+
+    let later = now + Duration::from_secs(30);
+    assert!(breaker.acquire(&config, later));
+    // Déjà ouvert : l'échec de l'essai prolonge l'ouverture sans la signaler à nouveau
+    assert!(!breaker.record(&config, true, later));
+    assert!(breaker.is_open(later + Duration::from_secs(29)));
+    assert!(!breaker.acquire(&config, later + Duration::from_secs(29)));
+    assert!(breaker.acquire(&config, later + Duration::from_secs(30)));
+}
+
+#[test]
+fn backoff_doubles_up_to_its_cap() {
+    let config = config();
+    assert_eq!(backoff(&config, 0), Duration::from_millis(200));
+    assert_eq!(backoff(&config, 1), Duration::from_millis(400));
+    assert_eq!(backoff(&config, 4), Duration::from_millis(3200));
+    assert_eq!(backoff(&config, 5), Duration::from_millis(5000));
+    assert_eq!(backoff(&config, 64), Duration::from_millis(5000));
+    assert_eq!(backoff(&config, u32::MAX), Duration::from_millis(5000));
+
+    let unbounded = UpstreamConfig {
+        initial_backoff_ms: u64::MAX,
+        max_backoff_ms: u64::MAX,
+        ..config
+    };
+    assert_eq!(backoff(&unbounded, 16), Duration::from_millis(u64::MAX));
+}
+
+#[test]
+fn blob_timeout_grows_with_blob_size() {
+    let config = UpstreamConfig {
+        timeout_seconds: 60,
+        min_blob_rate_kib: 256,
+        ..UpstreamConfig::default()
+    };
+    assert_eq!(blob_timeout(&config, 0), Duration::from_secs(60));
+    assert_eq!(blob_timeout(&config, 1), Duration::from_secs(61));
+    assert_eq!(
+        blob_timeout(&config, 100 * 1024 * 1024),
+        Duration::from_secs(60 + 400)
+    );
+
+    // Un débit nul dans la configuration reste borné
+    let zero = UpstreamConfig {
+        min_blob_rate_kib: 0,
+        ..config
+    };
+    assert_eq!(blob_timeout(&zero, 1024), Duration::from_secs(61));
+}
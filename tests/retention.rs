@@ -0,0 +1,193 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use poc::config::RetentionPolicy;
+use poc::history::recorded_at;
+use poc::retention::{evaluate, RepositoryRetention, TagDecision, Version};
+
+fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap()
+}
+
+fn policy(
+    keep_last_releases: Option<usize>,
+    prerelease_max_age_days: Option<i64>,
+) -> RetentionPolicy {
+    RetentionPolicy {
+        repositories: "*".to_string(),
+        keep_last_releases,
+        prerelease_max_age_days,
+        protected_tags: vec!["latest".to_string(), "stable*".to_string()],
+    }
+}
+
+// Version publiée `days` jours avant `now()`, None pour une date inconnue
+fn version(tag: &str, digest: &str, days: Option<i64>) -> Version {
+    Version {
+        tag: tag.to_string(),
+        digest: format!("sha256:{}", digest),
+        published: days.map(|days| now() - Duration::days(days)),
+    }
+}
+
+fn tags(decisions: &[TagDecision]) -> Vec<&str> {
+    let mut tags: Vec<&str> = decisions.iter().map(|d| d.tag.as_str()).collect();
+    tags.sort();
+    tags
+}
+
+fn run(policy: &RetentionPolicy, versions: Vec<Version>) -> RepositoryRetention {
+    evaluate(policy, "http-filter", versions, now())
+}
+
+#[test]
+fn protected_tags_keep_every_tag_of_their_manifest() {
+    let report = run(
+        &policy(Some(1), Some(1)),
+        vec![
+            version("2.0.0", "c", Some(1)),
+            version("1.0.0", "a", Some(30)),
+            version("stable", "a", Some(30)),
+            version("1.1.0-rc.1", "b", Some(30)),
+            version("latest", "b", Some(30)),
+            version("0.9.0", "d", Some(40)),
+        ],
+    );
+    assert_eq!(
+        tags(&report.kept),
+        ["1.0.0", "1.1.0-rc.1", "2.0.0", "latest", "stable"]
+    );
+    assert_eq!(tags(&report.deleted), ["0.9.0"]);
+}
+
+#[test]
+fn last_releases_are_counted_by_manifest() {
+    let report = run(
+        &policy(Some(2), None),
+        vec![
+            version("1.2.0", "c", Some(1)),
+            version("v1.2.0", "c", Some(1)),
+            version("1.1.0", "b", Some(10)),
+            version("1.0.0", "a", Some(20)),
+        ],
+    );
+    assert_eq!(tags(&report.kept), ["1.1.0", "1.2.0", "v1.2.0"]);
+    assert_eq!(tags(&report.deleted), ["1.0.0"]);
+}
+
+#[test]
+fn prereleases_expire_after_max_age() {
+    let versions = || {
+        vec![
+            version("1.3.0-rc.1", "a", Some(30)),
+            version("1.3.0-rc.2", "b", Some(2)),
+            version("1.3.0-rc.3+build.7", "c", Some(15)),
+        ]
+    };
+
+    let report = run(&policy(None, Some(14)), versions());
+    assert_eq!(tags(&report.kept), ["1.3.0-rc.2"]);
+    assert_eq!(tags(&report.deleted), ["1.3.0-rc.1", "1.3.0-rc.3+build.7"]);
+
+    // Sans limite d'âge, les pré-versions sont conservées
+    let report = run(&policy(None, None), versions());
+    assert_eq!(report.kept.len(), 3);
+    assert!(report.deleted.is_empty());
+}
+
+#[test]
+fn versions_without_publication_date_are_kept() {
+    let report = run(
+        &policy(Some(1), Some(1)),
+        vec![
+            version("2.0.0", "c", Some(1)),
+            version("1.0.0", "a", None),
+            version("1.1.0-rc.1", "b", None),
+        ],
+    );
+    assert_eq!(tags(&report.kept), ["1.0.0", "1.1.0-rc.1", "2.0.0"]);
+    assert!(report.deleted.is_empty());
+    let reason = |tag: &str| {
+        report
+            .kept
+            .iter()
+            .find(|d| d.tag == tag)
+            .map(|d| d.reason.clone())
+            .unwrap()
+    };
+    assert_eq!(reason("1.0.0"), "date de publication inconnue");
+    assert_eq!(reason("1.1.0-rc.1"), "date de publication inconnue");
+}
+
+#[test]
+fn only_semver_tags_are_releases_or_prereleases() {
+    let report = run(
+        &policy(Some(0), Some(0)),
+        vec![
+            version("feature-x", "a", Some(100)),
+            version("main", "b", Some(100)),
+            version("1.2", "c", Some(100)),
+            version("1.2.0-", "d", Some(100)),
+            version("01.2.x", "e", Some(100)),
+            version("1.2.0", "f", Some(100)),
+        ],
+    );
+    assert_eq!(
+        tags(&report.kept),
+        ["01.2.x", "1.2", "1.2.0-", "feature-x", "main"]
+    );
+    assert_eq!(tags(&report.deleted), ["1.2.0"]);
+    assert!(report
+        .kept
+        .iter()
+        .all(|decision| decision.reason == "tag hors semver"));
+}
+
+#[test]
+fn ties_on_publication_date_keep_the_highest_version() {
+    let report = run(
+        &policy(Some(1), None),
+        vec![
+            version("1.9.0", "a", Some(5)),
+            version("1.10.0", "b", Some(5)),
+            version("1.8.0", "c", Some(5)),
+        ],
+    );
+    assert_eq!(tags(&report.kept), ["1.10.0"]);
+    assert_eq!(tags(&report.deleted), ["1.8.0", "1.9.0"]);
+}
+
+#[test]
+fn publication_date_comes_from_the_latest_history_entry() {
+    let index = serde_json::json!({
+        "schemaVersion": 2,
+        "mediaType": "application/vnd.oci.image.index.v1+json",
+        "manifests": [
+            {
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "digest": "sha256:a",
+                "size": 10,
+                "annotations": {"com.aneocorp.history.recorded": "2025-01-01T00:00:00+00:00"}
+            },
+            {
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "digest": "sha256:b",
+                "size": 10
+            },
+            {
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "digest": "sha256:a",
+                "size": 10,
+                "annotations": {"com.aneocorp.history.recorded": "2025-05-31T12:00:00+00:00"}
+            }
+        ]
+    });
+    let index = serde_json::to_vec(&index).unwrap();
+
+    // Republié (rollback) : l'âge part de la dernière pose du tag
+    assert_eq!(
+        recorded_at(&index, "sha256:a"),
+        Some(now() - Duration::days(1))
+    );
+    // Version remplacée enregistrée sans date (tag posé hors de l'API)
+    assert_eq!(recorded_at(&index, "sha256:b"), None);
+    assert_eq!(recorded_at(&index, "sha256:c"), None);
+}
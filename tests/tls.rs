@@ -0,0 +1,141 @@
+use poc::config::TlsConfig;
+use poc::tls::{server_config, ReloadingCertificate};
+use rustls::crypto::ring::default_provider;
+use rustls::pki_types::{CertificateDer, ServerName};
+use rustls::{ClientConfig, ClientConnection, RootCertStore, ServerConnection};
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tempfile::TempDir;
+
+// Autorité `ca.pem` et deux paires serveur pour `localhost` signées par elle (EC P-256)
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/tls")
+        .join(name)
+}
+
+fn pem_certificate(name: &str) -> CertificateDer<'static> {
+    let file = fs::File::open(fixture(name)).unwrap();
+    rustls_pemfile::certs(&mut BufReader::new(file))
+        .next()
+        .unwrap()
+        .unwrap()
+}
+
+// Remplace un fichier servi ; la date de modification avance à chaque copie
+fn install(source: &str, target: &Path, age: u64) {
+    fs::copy(fixture(source), target).unwrap();
+    let file = fs::File::options().write(true).open(target).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(age))
+        .unwrap();
+}
+
+fn config(directory: &TempDir) -> TlsConfig {
+    TlsConfig {
+        cert_path: directory.path().join("server.pem").display().to_string(),
+        key_path: directory.path().join("server.key").display().to_string(),
+        reload_interval_seconds: 30,
+    }
+}
+
+// Poignée de main en mémoire ; retourne le certificat présenté par le serveur
+fn served_certificate(certificate: &Arc<ReloadingCertificate>) -> CertificateDer<'static> {
+    let mut roots = RootCertStore::empty();
+    roots.add(pem_certificate("ca.pem")).unwrap();
+    let client_config = ClientConfig::builder_with_provider(Arc::new(default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    let mut client = ClientConnection::new(
+        Arc::new(client_config),
+        ServerName::try_from("localhost").unwrap(),
+    )
+    .unwrap();
+    let mut server =
+        ServerConnection::new(Arc::new(server_config(certificate.clone()).unwrap())).unwrap();
+
+    while client.is_handshaking() || server.is_handshaking() {
+        let mut buffer = Vec::new();
+        client.write_tls(&mut buffer).unwrap();
+        server.read_tls(&mut buffer.as_slice()).unwrap();
+        server.process_new_packets().unwrap();
+
+        let mut buffer = Vec::new();
+        server.write_tls(&mut buffer).unwrap();
+        client.read_tls(&mut buffer.as_slice()).unwrap();
+        client.process_new_packets().unwrap();
+    }
+    client.peer_certificates().unwrap()[0].clone()
+}
+
+#[test]
+fn changed_files_replace_the_served_certificate() {
+    let directory = TempDir::new().unwrap();
+    let config = config(&directory);
+    install("server-a.pem", Path::new(&config.cert_path), 0);
+    install("server-a.key", Path::new(&config.key_path), 0);
+
+    let certificate = Arc::new(ReloadingCertificate::load(&config).unwrap());
+    assert_eq!(
+        served_certificate(&certificate),
+        pem_certificate("server-a.pem")
+    );
+    assert_eq!(certificate.reload_if_changed(), Ok(false));
+
+    install("server-b.pem", Path::new(&config.cert_path), 1);
+    install("server-b.key", Path::new(&config.key_path), 1);
+    assert_eq!(certificate.reload_if_changed(), Ok(true));
+    assert_eq!(
+        served_certificate(&certificate),
+        pem_certificate("server-b.pem")
+    );
+    assert_eq!(certificate.reload_if_changed(), Ok(false));
+}
+
+#[test]
+fn inconsistent_pair_keeps_the_previous_certificate() {
+    let directory = TempDir::new().unwrap();
+    let config = config(&directory);
+    install("server-a.pem", Path::new(&config.cert_path), 0);
+    install("server-a.key", Path::new(&config.key_path), 0);
+    let certificate = Arc::new(ReloadingCertificate::load(&config).unwrap());
+
+    // Certificat remplacé avant sa clé
+    install("server-b.pem", Path::new(&config.cert_path), 1);
+    let error = certificate.reload_if_changed().unwrap_err();
+    assert!(error.contains("ne correspondent pas"), "{}", error);
+    assert_eq!(
+        served_certificate(&certificate),
+        pem_certificate("server-a.pem")
+    );
+
+    // Fichier en cours d'écriture
+    fs::write(&config.key_path, "").unwrap();
+    assert!(certificate.reload_if_changed().is_err());
+    assert_eq!(
+        served_certificate(&certificate),
+        pem_certificate("server-a.pem")
+    );
+
+    install("server-b.key", Path::new(&config.key_path), 2);
+    assert_eq!(certificate.reload_if_changed(), Ok(true));
+    assert_eq!(
+        served_certificate(&certificate),
+        pem_certificate("server-b.pem")
+    );
+}
+
+#[test]
+fn missing_or_mismatched_files_are_refused_at_startup() {
+    let directory = TempDir::new().unwrap();
+    let config = config(&directory);
+    assert!(ReloadingCertificate::load(&config).is_err());
+
+    install("server-a.pem", Path::new(&config.cert_path), 0);
+    install("server-b.key", Path::new(&config.key_path), 0);
+    assert!(ReloadingCertificate::load(&config).is_err());
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use common::{app_state, component, credentials, FakeRegistry};
+use poc::config::{AppConfig, TrashConfig};
+use poc::manifest_builder::MANIFEST_MEDIA_TYPE;
+use poc::trash::{
+    list_trash, purge_expired, restore_version, trash_version, TrashEntry, TrashError,
+};
+use tempfile::TempDir;
+
+const REPOSITORY: &str = "components/echo";
+
+fn entry(result: Result<TrashEntry, TrashError>) -> TrashEntry {
+    match result {
+        Ok(entry) => entry,
+        Err(_) => panic!("mise en corbeille refusée"),
+    }
+}
+
+fn error(result: Result<impl Sized, TrashError>) -> &'static str {
+    match result {
+        Ok(_) => "ok",
+        Err(TrashError::NotFound) => "not found",
+        Err(TrashError::Conflict) => "conflict",
+        Err(TrashError::AlreadyTrashed) => "already trashed",
+        Err(TrashError::Expired) => "expired",
+        Err(TrashError::Registry(_)) => "registry",
+    }
+}
+
+// Rétention négative : les entrées sont expirées dès leur création
+fn expired_config() -> AppConfig {
+    AppConfig {
+        trash: TrashConfig {
+            retention_hours: -1,
+            ..TrashConfig::default()
+        },
+        ..AppConfig::default()
+    }
+}
+
+#[actix_web::test]
+async fn trashed_version_can_be_listed_and_restored() {
+    let registry = FakeRegistry::start().await;
+    let directory = TempDir::new().unwrap();
+    let state = app_state(&registry, &directory, AppConfig::default());
+
+    let manifest = component(&registry, REPOSITORY, "1.0.0");
+    let digest = registry.tag(REPOSITORY, "1.0.0", MANIFEST_MEDIA_TYPE, &manifest);
+
+    let trashed = entry(trash_version(&state, &credentials(), REPOSITORY, "1.0.0").await);
+    assert_eq!(trashed.reference, "1.0.0");
+    assert_eq!(trashed.digest, digest);
+    assert_eq!(
+        trashed.expires_at - trashed.deleted_at,
+        chrono::Duration::hours(7 * 24)
+    );
+    // Le tag disparaît, le manifest reste gardé par l'index de corbeille
+    assert_eq!(registry.tags(REPOSITORY), vec!["_trash.1.0.0".to_string()]);
+    assert!(registry.has_manifest(REPOSITORY, &digest));
+
+    let listed = list_trash(&state, &credentials(), REPOSITORY)
+        .await
+        .unwrap();
+    assert_eq!(listed.len(), 1);
+    assert_eq!(listed[0].reference, "1.0.0");
+    assert_eq!(listed[0].digest, digest);
+
+    let restored = match restore_version(&state, &credentials(), REPOSITORY, "1.0.0").await {
+        Ok(descriptor) => descriptor,
+        Err(_) => panic!("restauration refusée"),
+    };
+    assert_eq!(restored.digest, digest);
+    assert_eq!(
+        registry.tags(REPOSITORY),
+        vec!["1.0.0".to_string(), "_history.1.0.0".to_string()]
+    );
+    assert!(list_trash(&state, &credentials(), REPOSITORY)
+        .await
+        .unwrap()
+        .is_empty());
+    assert_eq!(
+        error(restore_version(&state, &credentials(), REPOSITORY, "1.0.0").await),
+        "not found"
+    );
+}
+
+#[actix_web::test]
+async fn a_pending_entry_is_never_overwritten() {
+    let registry = FakeRegistry::start().await;
+    let directory = TempDir::new().unwrap();
+    let state = app_state(&registry, &directory, AppConfig::default());
+
+    let first = component(&registry, REPOSITORY, "1.0.0-a");
+    let first_digest = registry.tag(REPOSITORY, "1.0.0", MANIFEST_MEDIA_TYPE, &first);
+    entry(trash_version(&state, &credentials(), REPOSITORY, "1.0.0").await);
+
+    // Republiée puis supprimée à nouveau : la première version reste seule en corbeille
+    let second = component(&registry, REPOSITORY, "1.0.0-b");
+    registry.tag(REPOSITORY, "1.0.0", MANIFEST_MEDIA_TYPE, &second);
+    assert_eq!(
+        error(trash_version(&state, &credentials(), REPOSITORY, "1.0.0").await),
+        "already trashed"
+    );
+    assert!(registry.tags(REPOSITORY).contains(&"1.0.0".to_string()));
+
+    // La version republiée bloque aussi la restauration
+    assert_eq!(
+        error(restore_version(&state, &credentials(), REPOSITORY, "1.0.0").await),
+        "conflict"
+    );
+    let listed = list_trash(&state, &credentials(), REPOSITORY)
+        .await
+        .unwrap();
+    assert_eq!(listed[0].digest, first_digest);
+
+    assert_eq!(
+        error(trash_version(&state, &credentials(), REPOSITORY, "2.0.0").await),
+        "not found"
+    );
+}
+
+#[actix_web::test]
+async fn expired_entries_are_purged_and_no_longer_restorable() {
+    let registry = FakeRegistry::start().await;
+    let directory = TempDir::new().unwrap();
+    let state = app_state(&registry, &directory, expired_config());
+
+    let manifest = component(&registry, REPOSITORY, "1.0.0");
+    let digest = registry.tag(REPOSITORY, "1.0.0", MANIFEST_MEDIA_TYPE, &manifest);
+    entry(trash_version(&state, &credentials(), REPOSITORY, "1.0.0").await);
+
+    assert_eq!(
+        error(restore_version(&state, &credentials(), REPOSITORY, "1.0.0").await),
+        "expired"
+    );
+
+    let reports = purge_expired(&state, &credentials()).await.unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].tags, vec!["_trash.1.0.0".to_string()]);
+    assert!(reports[0].manifests.contains(&digest));
+    assert_eq!(reports[0].blobs.len(), 2);
+    assert!(registry.tags(REPOSITORY).is_empty());
+    assert!(!registry.has_manifest(REPOSITORY, &digest));
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use actix_web::http::header::HeaderMap;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use chrono::{Duration, Utc};
+use common::{app_state, FakeRegistry};
+use poc::config::{AppConfig, WebhookConfig, WebhookEndpoint};
+use poc::webhooks::{
+    backoff, deliver_due, signature, DeliveryStatus, WebhookEvent, WebhookPayload, DELIVERY_HEADER,
+    EVENT_HEADER, SIGNATURE_HEADER,
+};
+use std::sync::{Arc, Mutex};
+use tempfile::TempDir;
+
+#[test]
+fn signature_is_hmac_sha256_of_the_body() {
+    // RFC 4231, cas de test 2
+    assert_eq!(
+        signature("Jefe", b"what do ya want for nothing?"),
+        "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+    assert_ne!(signature("autre", b"{}"), signature("secret", b"{}"));
+}
+
+#[test]
+fn backoff_doubles_after_each_failure_up_to_its_cap() {
+    let config = WebhookConfig {
+        initial_backoff_seconds: 10,
+        max_backoff_seconds: 300,
+        ..WebhookConfig::default()
+    };
+    assert_eq!(backoff(&config, 1), Duration::seconds(10));
+    assert_eq!(backoff(&config, 2), Duration::seconds(20));
+    assert_eq!(backoff(&config, 5), Duration::seconds(160));
+    assert_eq!(backoff(&config, 6), Duration::seconds(300));
+    assert_eq!(backoff(&config, u32::MAX), Duration::seconds(300));
+}
+
+type Received = Arc<Mutex<Vec<(HeaderMap, web::Bytes)>>>;
+
+// Destinataire des webhooks : `/ok` accepte, les autres chemins répondent 503
+async fn receiver() -> (String, Received) {
+    let received: Received = Arc::default();
+    let data = web::Data::new(received.clone());
+    let server = HttpServer::new(move || {
+        App::new().app_data(data.clone()).default_service(web::to(
+            |request: HttpRequest, body: web::Bytes, received: web::Data<Received>| async move {
+                received
+                    .lock()
+                    .unwrap()
+                    .push((request.headers().clone(), body));
+                match request.path() {
+                    "/ok" => HttpResponse::Ok().finish(),
+                    _ => HttpResponse::ServiceUnavailable().finish(),
+                }
+            },
+        ))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+    let url = format!("http://{}", server.addrs()[0]);
+    actix_web::rt::spawn(server.run());
+    (url, received)
+}
+
+fn endpoint(name: &str, url: String, events: Vec<WebhookEvent>) -> WebhookEndpoint {
+    WebhookEndpoint {
+        name: name.to_string(),
+        url,
+        secret: Some("secret".to_string()),
+        events,
+        repositories: "team-a-*".to_string(),
+    }
+}
+
+fn payload(event: WebhookEvent, repository: &str) -> WebhookPayload {
+    WebhookPayload {
+        event,
+        timestamp: Utc::now(),
+        repository: repository.to_string(),
+        version: Some("1.0.0".to_string()),
+        digest: None,
+        component_type: None,
+        actor: "ci".to_string(),
+    }
+}
+
+#[actix_web::test]
+async fn deliveries_are_signed_and_failures_rescheduled() {
+    let registry = FakeRegistry::start().await;
+    let (url, received) = receiver().await;
+    let directory = TempDir::new().unwrap();
+    let config = AppConfig {
+        webhooks: WebhookConfig {
+            endpoints: vec![
+                endpoint("ok", format!("{}/ok", url), Vec::new()),
+                endpoint(
+                    "down",
+                    format!("{}/down", url),
+                    vec![WebhookEvent::Published],
+                ),
+            ],
+            max_attempts: 2,
+            initial_backoff_seconds: 60,
+            ..WebhookConfig::default()
+        },
+        ..AppConfig::default()
+    };
+    let state = app_state(&registry, &directory, config);
+    let queue = &state.webhook_queue;
+
+    // Dépôt hors du motif, puis événement filtré par l'endpoint `down`
+    queue.enqueue(
+        &state.webhook_config,
+        payload(WebhookEvent::Published, "team-b-echo"),
+    );
+    queue.enqueue(
+        &state.webhook_config,
+        payload(WebhookEvent::Deleted, "team-a-echo"),
+    );
+    queue.enqueue(
+        &state.webhook_config,
+        payload(WebhookEvent::Published, "team-a-echo"),
+    );
+    assert_eq!(queue.deliveries(None, None).len(), 3);
+
+    let started = Utc::now();
+    assert_eq!(deliver_due(&state).await, 3);
+    {
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 3);
+        for (headers, body) in received.iter() {
+            let header = |name: &str| headers.get(name).unwrap().to_str().unwrap();
+            assert_eq!(header(SIGNATURE_HEADER), signature("secret", body));
+            assert!(header(DELIVERY_HEADER).contains('-'));
+            let payload: WebhookPayload = serde_json::from_slice(body).unwrap();
+            assert_eq!(header(EVENT_HEADER), payload.event.as_str());
+        }
+    }
+
+    let delivered = queue.deliveries(Some(DeliveryStatus::Delivered), Some("ok"));
+    assert_eq!(delivered.len(), 2);
+    assert!(delivered
+        .iter()
+        .all(|delivery| delivery.last_status == Some(200)));
+
+    let pending = queue.deliveries(Some(DeliveryStatus::Pending), None);
+    assert_eq!(pending.len(), 1);
+    assert_eq!(pending[0].endpoint, "down");
+    assert_eq!(pending[0].attempts, 1);
+    assert_eq!(pending[0].last_status, Some(503));
+    let next = pending[0].next_attempt_at.unwrap();
+    assert!(next >= started + Duration::seconds(60));
+    assert!(next <= Utc::now() + Duration::seconds(60));
+
+    // Pas encore à échéance : rien n'est renvoyé
+    assert_eq!(deliver_due(&state).await, 0);
+    assert_eq!(received.lock().unwrap().len(), 3);
+}
+
+#[actix_web::test]
+async fn delivery_fails_after_its_last_attempt() {
+    let registry = FakeRegistry::start().await;
+    let (url, received) = receiver().await;
+    let directory = TempDir::new().unwrap();
+    let config = AppConfig {
+        webhooks: WebhookConfig {
+            endpoints: vec![endpoint("down", format!("{}/down", url), Vec::new())],
+            max_attempts: 1,
+            ..WebhookConfig::default()
+        },
+        ..AppConfig::default()
+    };
+    let state = app_state(&registry, &directory, config);
+
+    state.webhook_queue.enqueue(
+        &state.webhook_config,
+        payload(WebhookEvent::Updated, "team-a-echo"),
+    );
+    assert_eq!(deliver_due(&state).await, 1);
+
+    let failed = state
+        .webhook_queue
+        .deliveries(Some(DeliveryStatus::Failed), None);
+    assert_eq!(failed.len(), 1);
+    assert!(failed[0].next_attempt_at.is_none());
+    assert!(failed[0].last_error.as_deref().unwrap().contains("503"));
+    assert_eq!(deliver_due(&state).await, 0);
+    assert_eq!(received.lock().unwrap().len(), 1);
+}